@@ -1,34 +1,65 @@
 use anyhow::{anyhow, Result};
-use rnote::{app, process};
+use rnote::{app, config, process};
 
 mod rnote;
 
-/// Check if variable `EDITOR` and `XDG_DATA_HOME` are set.
-fn check() -> Result<()> {
-    let editor = std::env::var("EDITOR").unwrap_or("".to_owned());
-    let data_home = std::env::var("XDG_DATA_HOME").unwrap_or("".to_owned());
-    if editor.is_empty() || data_home.is_empty() {
-        Err(anyhow!(
-            "Please make sure variables EDITOR and XDG_DATA_HOME are set.\n\nExample:\n\texport\
-            XDG_DATA_HOME=$HOME/.local/share\n\texport EDITOR=/usr/bin/vim"
-        ))
-    } else {
-        Ok(())
+/// Subcommands that always open an editor.
+const EDITOR_COMMANDS: [&str; 5] = ["new", "edit", "today", "yesterday", "journal"];
+
+/// Check if the notes directory is known, and an editor too if `command`
+/// needs one.
+fn check(command: &str) -> Result<()> {
+    let config = config::get();
+    if config.notes_dir().is_empty() {
+        return Err(anyhow!(
+            "Could not find where to store notes. Please set HOME or XDG_DATA_HOME, or run\n\n\t\
+            rnote config set notes_dir <directory>"
+        ));
+    }
+    if EDITOR_COMMANDS.contains(&command) && config.editor().is_empty() {
+        return Err(anyhow!(
+            "No editor found. Please set VISUAL or EDITOR, or run\n\n\t\
+            rnote config set editor <command>"
+        ));
     }
+    Ok(())
 }
 
 fn main() -> Result<()> {
     let mut app = app::make_app();
-    check()?;
+    let matches = app::make_app().get_matches();
+    process::configure(&matches)?;
+    match matches.subcommand() {
+        ("config", Some(m)) => return process::config(m),
+        ("notebook", Some(m)) => return process::notebook(m),
+        _ => {}
+    }
+    check(matches.subcommand_name().unwrap_or_default())?;
 
-    match rnote::app::make_app().get_matches().subcommand() {
+    match matches.subcommand() {
         ("new", Some(m)) => process::new(m)?,
         ("remove", Some(m)) => process::remove(m)?,
         ("edit", Some(m)) => process::edit(m)?,
         ("list", Some(m)) => process::list(m)?,
         ("show", Some(m)) => process::show(m)?,
         ("search", Some(m)) => process::search(m)?,
-        ("panic", _) => process::panic()?,
+        ("tags", Some(m)) => process::tags(m)?,
+        ("reindex", _) => process::reindex()?,
+        ("migrate", Some(m)) => process::migrate(m)?,
+        ("trash", Some(m)) => process::trash(m)?,
+        ("git", Some(m)) => process::git(m)?,
+        ("rename", Some(m)) => process::rename(m)?,
+        ("move", Some(m)) => process::move_note(m)?,
+        ("category", Some(m)) => process::category(m)?,
+        ("history", Some(m)) => process::history(m)?,
+        ("diff", Some(m)) => process::diff(m)?,
+        ("restore", Some(m)) => process::restore(m)?,
+        ("template", Some(m)) => process::template(m)?,
+        (c @ ("today" | "yesterday" | "journal"), Some(m)) => process::journal(c, m)?,
+        ("calendar", Some(m)) => process::calendar(m)?,
+        ("links", Some(m)) => process::links(m)?,
+        ("backlinks", Some(m)) => process::backlinks(m)?,
+        ("panic", Some(m)) => process::panic(m)?,
         _ => app.print_long_help()?,
     };
 
@@ -42,6 +73,6 @@ mod tests {
     #[test]
     #[ignore]
     fn check_test() {
-        assert!(check().is_ok());
+        assert!(check("new").is_ok());
     }
 }
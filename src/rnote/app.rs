@@ -1,5 +1,90 @@
 pub use clap::{App, AppSettings, Arg, SubCommand};
 
+/// Argument `--format` to print results instead of prompting.
+fn format_arg() -> Arg<'static, 'static> {
+    Arg::with_name("format")
+        .help("Print results in the given format instead of prompting.")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["plain", "json", "tsv"])
+}
+
+/// Argument `--json`, a shorthand for `--format json`.
+fn json_arg() -> Arg<'static, 'static> {
+    Arg::with_name("json")
+        .help("Same as --format json.")
+        .long("json")
+        .conflicts_with("format")
+}
+
+/// Argument `--tag` for commands filtering notes by tags.
+fn tag_arg() -> Arg<'static, 'static> {
+    Arg::with_name("tag")
+        .help("Only notes with the given tag(s).")
+        .short("t")
+        .long("tag")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .use_delimiter(true)
+}
+
+/// Argument `--any` to match any of the tags given with `--tag`.
+fn any_arg() -> Arg<'static, 'static> {
+    Arg::with_name("any")
+        .help("Match notes having any of the given tags instead of all of them.")
+        .long("any")
+        .requires("tag")
+}
+
+/// Argument `--exact` for commands taking a note name.
+fn exact_arg() -> Arg<'static, 'static> {
+    Arg::with_name("exact")
+        .help("Only match notes named exactly <name>.")
+        .long("exact")
+}
+
+/// Argument `--yes` to skip confirmation of destructive commands.
+fn yes_arg() -> Arg<'static, 'static> {
+    Arg::with_name("yes")
+        .help("Do not ask for confirmation.")
+        .short("y")
+        .long("yes")
+}
+
+/// Argument `--dry-run` to only print what destructive commands would do.
+fn dry_run_arg() -> Arg<'static, 'static> {
+    Arg::with_name("dry-run")
+        .help("Only print what would be deleted.")
+        .long("dry-run")
+        .conflicts_with("yes")
+}
+
+/// Argument `--date` for commands filtering notes by the date of their header.
+fn date_arg() -> Arg<'static, 'static> {
+    Arg::with_name("date")
+        .help("Only notes dated the given day, e.g. 2021-05-03, today or 2w for 2 weeks ago.")
+        .short("d")
+        .long("date")
+        .takes_value(true)
+}
+
+/// Argument `--since`, the first day of notes to keep.
+fn since_arg() -> Arg<'static, 'static> {
+    Arg::with_name("since")
+        .help("Only notes dated on or after the given day, e.g. 2021-01-01 or 2w.")
+        .long("since")
+        .takes_value(true)
+}
+
+/// Argument `--until`, the last day of notes to keep.
+fn until_arg() -> Arg<'static, 'static> {
+    Arg::with_name("until")
+        .help("Only notes dated on or before the given day, e.g. 2021-03-31 or 1d.")
+        .long("until")
+        .takes_value(true)
+}
+
 /// Initialize all possible arguments.
 pub fn make_app() -> App<'static, 'static> {
     App::new("rnote")
@@ -7,6 +92,28 @@ pub fn make_app() -> App<'static, 'static> {
         .author("Volodymyr Patuta")
         .about("Minimal note taking cli tool.")
         .setting(AppSettings::ArgRequiredElseHelp)
+        .arg(
+            Arg::with_name("notes-dir")
+                .help("Root directory of all notes, overriding the configuration.")
+                .long("notes-dir")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("notebook")
+                .help("Notebook to use instead of the default one.")
+                .long("notebook")
+                .takes_value(true)
+                .conflicts_with("notes-dir")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("editor")
+                .help("Command used to edit notes, overriding the configuration.")
+                .long("editor")
+                .takes_value(true)
+                .global(true),
+        )
         .subcommand(
             SubCommand::with_name("new")
                 .alias("n")
@@ -20,6 +127,23 @@ pub fn make_app() -> App<'static, 'static> {
                     Arg::with_name("category")
                         .help("Create note in category.")
                         .index(2),
+                )
+                .arg(tag_arg().help("Tag the note. Can be repeated or comma separated."))
+                .arg(
+                    Arg::with_name("template")
+                        .help("Create the note from a template.")
+                        .short("T")
+                        .long("template")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("var")
+                        .help("Set a template variable instead of being prompted, as name=value.")
+                        .long("var")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .requires("template"),
                 ),
         )
         .subcommand(
@@ -27,14 +151,18 @@ pub fn make_app() -> App<'static, 'static> {
                 .alias("r")
                 .alias("rm")
                 .about("Remove a note.")
+                .arg(yes_arg())
+                .arg(dry_run_arg())
+                .arg(exact_arg())
                 .arg(Arg::with_name("name").help("Name of the note."))
                 .arg(
-                    Arg::with_name("date")
-                        .help("Delete all notes created at given date.")
-                        .short("d")
-                        .conflicts_with("category")
-                        .long("date"),
+                    date_arg()
+                        .help("Delete all notes dated the given day, or <name> if no day is given.")
+                        .min_values(0)
+                        .conflicts_with("category"),
                 )
+                .arg(since_arg().conflicts_with("category"))
+                .arg(until_arg().conflicts_with("category"))
                 .arg(
                     Arg::with_name("category")
                         .help("Delete all notes from a given category / Delete a category")
@@ -47,6 +175,7 @@ pub fn make_app() -> App<'static, 'static> {
             SubCommand::with_name("edit")
                 .alias("e")
                 .about("Edit a note.")
+                .arg(exact_arg())
                 .arg(Arg::with_name("name").help("Name of the note.")),
         )
         .subcommand(
@@ -64,37 +193,346 @@ pub fn make_app() -> App<'static, 'static> {
                         .help("List all notes from a category.")
                         .short("c")
                         .long("category"),
-                ),
+                )
+                .arg(date_arg())
+                .arg(since_arg())
+                .arg(until_arg())
+                .arg(
+                    Arg::with_name("sort")
+                        .help("Sort notes by date, title or last edit.")
+                        .long("sort")
+                        .takes_value(true)
+                        .possible_values(&["date", "title", "updated"]),
+                )
+                .arg(format_arg())
+                .arg(json_arg())
+                .arg(tag_arg())
+                .arg(any_arg()),
         )
         .subcommand(
             SubCommand::with_name("search")
                 .alias("s")
                 .about("Search a note.")
+                .arg(format_arg())
+                .arg(json_arg())
+                .arg(exact_arg())
                 .arg(
                     Arg::with_name("word")
                         .help("Search by word.")
                         .short("w")
                         .long("word"),
                 )
+                .arg(
+                    Arg::with_name("query")
+                        .help("Search with a query, e.g. 'rust AND (tokio OR async) -draft tag:work'.")
+                        .short("q")
+                        .long("query")
+                        .conflicts_with("word"),
+                )
+                .arg(
+                    Arg::with_name("regex")
+                        .help("Print lines matching a regular expression.")
+                        .short("r")
+                        .long("regex")
+                        .conflicts_with("query"),
+                )
+                .arg(
+                    Arg::with_name("ignore-case")
+                        .help("Print matching lines, ignoring case.")
+                        .short("i")
+                        .long("ignore-case")
+                        .conflicts_with("query"),
+                )
+                .arg(
+                    Arg::with_name("whole-word")
+                        .help("Print lines where the string matches a whole word.")
+                        .long("whole-word")
+                        .conflicts_with("query"),
+                )
+                .arg(tag_arg())
+                .arg(any_arg())
                 .arg(Arg::with_name("name").help("Name of the note.")),
         )
         .subcommand(
             SubCommand::with_name("show")
                 .about("Show note(s) in TextView.")
+                .arg(exact_arg())
                 .arg(
                     Arg::with_name("all")
-                        .help("Show all notes.")
+                        .help("Browse all notes.")
                         .short("a")
                         .conflicts_with("name")
                         .long("all"),
                 )
                 .arg(
                     Arg::with_name("category")
-                        .help("Show all notes from a category/date.")
+                        .help("Browse all notes from a category/date.")
                         .short("c")
                         .long("category"),
                 )
+                .arg(date_arg())
+                .arg(since_arg())
+                .arg(until_arg())
+                .arg(tag_arg())
+                .arg(any_arg())
+                .arg(Arg::with_name("name").help("Name of the note.")),
+        )
+        .subcommand(
+            SubCommand::with_name("reindex").about("Rebuild the search index from scratch."),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Rewrite the dates of note headers as ISO 8601 timestamps.")
+                .arg(yes_arg())
+                .arg(dry_run_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("tags")
+                .about("List all tags with their number of notes.")
+                .arg(format_arg())
+                .arg(json_arg()),
+        )
+        .subcommand(
+            SubCommand::with_name("trash")
+                .about("List, restore or permanently delete removed notes.")
+                .subcommand(SubCommand::with_name("list").about("List notes in the trash."))
+                .subcommand(
+                    SubCommand::with_name("restore")
+                        .about("Restore a note to where it was.")
+                        .arg(Arg::with_name("name").help("Name or original path of the note.")),
+                )
+                .subcommand(
+                    SubCommand::with_name("empty")
+                        .about("Permanently delete notes from the trash.")
+                        .arg(yes_arg())
+                        .arg(dry_run_arg())
+                        .arg(
+                            Arg::with_name("older-than")
+                                .help("Only notes deleted longer ago than e.g. 30d, 2w or 12h.")
+                                .long("older-than")
+                                .takes_value(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("git")
+                .about("Manage versioning of notes with git.")
+                .subcommand(
+                    SubCommand::with_name("init")
+                        .about("Turn the notes directory into a git repository."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rename")
+                .about("Rename a note and update the links to it.")
+                .arg(exact_arg())
+                .arg(Arg::with_name("name").help("Name of the note.").required(true))
+                .arg(
+                    Arg::with_name("new_name")
+                        .help("New name of the note.")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("move")
+                .alias("mv")
+                .about("Move a note to another category and update the links to it.")
+                .arg(exact_arg())
+                .arg(Arg::with_name("name").help("Name of the note.").required(true))
+                .arg(
+                    Arg::with_name("category")
+                        .help("Category to move the note to.")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("category")
+                .about("List, rename or merge categories.")
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List categories as a tree with their number of notes."),
+                )
+                .subcommand(
+                    SubCommand::with_name("rename")
+                        .about("Rename a category and update the links to its notes.")
+                        .arg(
+                            Arg::with_name("old")
+                                .help("Category to rename, e.g. work/projects.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("new")
+                                .help("New name of the category.")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("merge")
+                        .about("Move every note of a category to another one.")
+                        .arg(
+                            Arg::with_name("suffix")
+                                .help("Add a suffix to notes whose name is taken instead of asking.")
+                                .long("suffix"),
+                        )
+                        .arg(
+                            Arg::with_name("src")
+                                .help("Category to empty.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("dst")
+                                .help("Category to move the notes to.")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Show the commits that changed a note.")
+                .arg(exact_arg())
                 .arg(Arg::with_name("name").help("Name of the note.")),
         )
-        .subcommand(SubCommand::with_name("panic").about("Delete all notes."))
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("Show changes of a note since a revision, or its last change.")
+                .arg(exact_arg())
+                .arg(Arg::with_name("name").help("Name of the note.").required(true))
+                .arg(Arg::with_name("rev").help("Revision to compare with.")),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Restore a note to its content at a revision.")
+                .arg(exact_arg())
+                .arg(
+                    Arg::with_name("rev")
+                        .help("Revision to restore, as shown by `rnote history`.")
+                        .long("rev")
+                        .takes_value(true)
+                        .required(true),
+                )
+                .arg(Arg::with_name("name").help("Name of the note.")),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Show or change the configuration.")
+                .subcommand(
+                    SubCommand::with_name("path").about("Print the path of the config file."),
+                )
+                .subcommand(SubCommand::with_name("list").about("List every setting."))
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Print the value of a setting.")
+                        .arg(Arg::with_name("key").help("Name of the setting.").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Change a setting in the config file.")
+                        .arg(Arg::with_name("key").help("Name of the setting.").required(true))
+                        .arg(
+                            Arg::with_name("value")
+                                .help("New value of the setting.")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("notebook")
+                .about("Manage notebooks, each with its own directory.")
+                .subcommand(SubCommand::with_name("list").about("List notebooks."))
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Add a notebook.")
+                        .arg(
+                            Arg::with_name("name")
+                                .help("Name of the notebook.")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("path")
+                                .help("Directory of the notebook.")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Forget a notebook, leaving its notes on disk.")
+                        .arg(
+                            Arg::with_name("name")
+                                .help("Name of the notebook.")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("default")
+                        .about("Print or set the default notebook.")
+                        .arg(Arg::with_name("name").help(
+                            "Name of the notebook, or \"\" to use notes_dir again.",
+                        )),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("template")
+                .about("List, show or edit note templates.")
+                .subcommand(SubCommand::with_name("list").about("List templates."))
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Print a template.")
+                        .arg(
+                            Arg::with_name("name")
+                                .help("Name of the template.")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("edit")
+                        .about("Edit a template, creating it if needed.")
+                        .arg(
+                            Arg::with_name("name")
+                                .help("Name of the template.")
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(SubCommand::with_name("today").about("Open today's journal entry."))
+        .subcommand(SubCommand::with_name("yesterday").about("Open yesterday's journal entry."))
+        .subcommand(
+            SubCommand::with_name("journal")
+                .about("Open the journal entry of a day, or show the entries of a week.")
+                .arg(
+                    Arg::with_name("week")
+                        .help("Show the entries of the week of the day.")
+                        .short("w")
+                        .long("week"),
+                )
+                .arg(Arg::with_name("date").help("Day of the entry, e.g. 2021-05-03. Today by default.")),
+        )
+        .subcommand(
+            SubCommand::with_name("calendar")
+                .alias("cal")
+                .about("Show the days of a month having a journal entry.")
+                .arg(Arg::with_name("month").help("Month to show, e.g. 2021-05. This month by default.")),
+        )
+        .subcommand(
+            SubCommand::with_name("links")
+                .about("Show the [[links]] of a note, or of every note.")
+                .arg(exact_arg())
+                .arg(
+                    Arg::with_name("dangling")
+                        .help("Only show links to notes that do not exist.")
+                        .long("dangling"),
+                )
+                .arg(Arg::with_name("name").help("Name of the note.")),
+        )
+        .subcommand(
+            SubCommand::with_name("backlinks")
+                .about("Show the notes linking to a note.")
+                .arg(exact_arg())
+                .arg(Arg::with_name("name").help("Name of the note.").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("panic")
+                .about("Delete all notes.")
+                .arg(yes_arg())
+                .arg(dry_run_arg()),
+        )
 }
@@ -0,0 +1,443 @@
+use crate::rnote::{
+    config, fuzzy,
+    note::{Note, Sort},
+    notes,
+    show::{self, Page},
+};
+use anyhow::Result;
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event, KeyCode, KeyCode::*, KeyEvent, KeyModifiers},
+    queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, Clear, ClearType},
+};
+use std::{io::Write, path::Path};
+use termimad::{Area, MadSkin, MadView};
+
+/// What the status line is asking for.
+#[derive(Debug, Clone, PartialEq)]
+enum Prompt {
+    None,
+    Filter,
+    Delete,
+    /// Category typed so far.
+    Move(String),
+}
+
+/// A line of the note list.
+#[derive(Debug, Clone, PartialEq)]
+enum Row {
+    Category(String),
+    /// Index of the note in the listed notes.
+    Note(usize),
+}
+
+/// Get the indices of the `notes` matching `filter`, sorted by category then
+/// by `sort`.
+fn list(notes: &[Note], filter: &str, sort: Sort) -> Vec<usize> {
+    let mut shown: Vec<usize> = (0..notes.len())
+        .filter(|i| {
+            let note = &notes[*i];
+            filter.is_empty()
+                || fuzzy::score(filter, &note.front.title).is_some()
+                || fuzzy::score(filter, &note.relative_path()).is_some()
+        })
+        .collect();
+    shown.sort_by(|a, b| {
+        let (a, b) = (&notes[*a], &notes[*b]);
+        a.category.cmp(&b.category).then_with(|| sort.compare(a, b))
+    });
+    shown
+}
+
+/// Get the rows showing the listed notes `shown` under their category.
+fn rows(notes: &[Note], shown: &[usize]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    for (i, note) in shown.iter().map(|n| &notes[*n]).enumerate() {
+        let first = i == 0 || notes[shown[i - 1]].category != note.category;
+        if first {
+            rows.push(Row::Category(note.category.clone()));
+        }
+        rows.push(Row::Note(i));
+    }
+    rows
+}
+
+/// State of the browser.
+struct Browser {
+    skin: MadSkin,
+    notes: Vec<Note>,
+    filter: String,
+    sort: Sort,
+    /// Indices of the notes listed, in order.
+    shown: Vec<usize>,
+    /// Index in `shown` of the selected note.
+    selected: usize,
+    /// First row of the list on screen.
+    top: usize,
+    /// Selected note as shown on the right.
+    preview: Option<MadView>,
+    prompt: Prompt,
+    /// Outcome of the last action.
+    message: String,
+}
+
+impl Browser {
+    fn new(skin: MadSkin, notes: Vec<Note>) -> Self {
+        let mut browser = Browser {
+            skin,
+            notes,
+            filter: String::new(),
+            sort: Sort::Date,
+            shown: Vec::new(),
+            selected: 0,
+            top: 0,
+            preview: None,
+            prompt: Prompt::None,
+            message: String::new(),
+        };
+        browser.update();
+        browser
+    }
+
+    /// Get the width of the list and the area of the preview.
+    fn layout() -> Result<(u16, Area)> {
+        let (width, height) = terminal::size()?;
+        let list = (width / 3).clamp(20.min(width), 40);
+        let left = list + 2;
+        let preview = Area::new(
+            left,
+            0,
+            width.saturating_sub(left + 1),
+            height.saturating_sub(1),
+        );
+        Ok((list, preview))
+    }
+
+    /// Get the selected note.
+    fn current(&self) -> Option<&Note> {
+        self.shown.get(self.selected).map(|i| &self.notes[*i])
+    }
+
+    /// List the notes again, keeping the selected one if it is still listed.
+    fn update(&mut self) {
+        let current = self.shown.get(self.selected).copied();
+        self.shown = list(&self.notes, &self.filter, self.sort);
+        self.selected = current
+            .and_then(|c| self.shown.iter().position(|i| *i == c))
+            .unwrap_or(0);
+        self.update_preview();
+    }
+
+    /// Show the selected note on the right.
+    fn update_preview(&mut self) {
+        self.preview = match (self.current(), Browser::layout()) {
+            (Some(note), Ok((_, area))) => {
+                Some(MadView::from(note.to_markdown(), area, self.skin.clone()))
+            }
+            _ => None,
+        };
+    }
+
+    /// Select the note `offset` notes away from the selected one.
+    fn select(&mut self, offset: i64) {
+        if self.shown.is_empty() {
+            return;
+        }
+        let last = self.shown.len() as i64 - 1;
+        self.selected = (self.selected as i64 + offset).clamp(0, last) as usize;
+        self.update_preview();
+    }
+
+    /// Read the note at `path` again into the note `index`, or drop it if it
+    /// can't be read anymore.
+    fn reload(&mut self, index: usize, path: &Path) -> Result<()> {
+        let base = notes::get_base_path()?;
+        match Note::load(path, Path::new(&base)) {
+            Ok(note) => self.notes[index] = note,
+            Err(_) => {
+                self.notes.remove(index);
+            }
+        }
+        self.update();
+        Ok(())
+    }
+
+    /// Write the list of notes on the left, scrolled to the selected one.
+    fn write_list<W: Write>(&mut self, w: &mut W, width: u16, height: u16) -> Result<()> {
+        let rows = rows(&self.notes, &self.shown);
+        let selected = rows
+            .iter()
+            .position(|r| *r == Row::Note(self.selected))
+            .unwrap_or(0);
+        // Keep the category of the selected note in sight.
+        let first = selected.saturating_sub(1);
+        let height = usize::from(height);
+        if first < self.top {
+            self.top = first;
+        } else if selected >= self.top + height {
+            self.top = selected + 1 - height;
+        }
+        let width = usize::from(width);
+        let date_format = &config::get().display_date_format;
+        for y in 0..height {
+            queue!(w, MoveTo(0, y as u16))?;
+            let (text, style) = match rows.get(self.top + y) {
+                Some(Row::Category(c)) if c.is_empty() => {
+                    ("(no category)".to_owned(), Attribute::Bold)
+                }
+                Some(Row::Category(c)) => (c.clone(), Attribute::Bold),
+                Some(Row::Note(i)) => {
+                    let note = &self.notes[self.shown[*i]];
+                    let date = note
+                        .front
+                        .date
+                        .map(|d| d.format(date_format).to_string())
+                        .unwrap_or_default();
+                    let room = width.saturating_sub(date.chars().count() + 3);
+                    let title: String = note.front.title.chars().take(room).collect();
+                    let line = format!("  {:room$} {}", title, date, room = room);
+                    let style = match *i == self.selected {
+                        true => Attribute::Reverse,
+                        false => Attribute::Reset,
+                    };
+                    (line, style)
+                }
+                None if y == 0 && rows.is_empty() => ("No notes.".to_owned(), Attribute::Reset),
+                None => (String::new(), Attribute::Reset),
+            };
+            let text: String = text.chars().take(width).collect();
+            queue!(
+                w,
+                SetAttribute(style),
+                Print(format!("{:width$}", text, width = width)),
+                SetAttribute(Attribute::Reset),
+                Print(" │")
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the status line: the prompt, else the last message, else help.
+    fn write_status<W: Write>(&self, w: &mut W) -> Result<()> {
+        let path = self
+            .current()
+            .map(|n| n.relative_path())
+            .unwrap_or_default();
+        let status = match &self.prompt {
+            Prompt::Filter => format!("/{}", self.filter),
+            Prompt::Delete => format!("Move {} to trash? (y/n)", path),
+            Prompt::Move(category) => format!("Move {} to category: {}", path, category),
+            Prompt::None if !self.message.is_empty() => format!(" {}", self.message),
+            Prompt::None => format!(
+                " {} note(s), by {}  / filter  s sort  Enter open  e edit  d delete  m move  q quit",
+                self.shown.len(),
+                self.sort
+            ),
+        };
+        show::write_status_line(w, &status)
+    }
+
+    /// Draw the whole browser.
+    fn write<W: Write>(&mut self, w: &mut W) -> Result<()> {
+        let (width, preview) = Browser::layout()?;
+        self.write_list(w, width, preview.height)?;
+        match &self.preview {
+            Some(view) => view.write_on(w)?,
+            None => {
+                for y in 0..preview.height {
+                    queue!(w, MoveTo(preview.left, y), Clear(ClearType::UntilNewLine))?;
+                }
+            }
+        }
+        self.write_status(w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Run `action` on the selected note, showing its error if it fails.
+    fn act(&mut self, action: impl FnOnce(&mut Self, usize) -> Result<String>) {
+        let index = match self.shown.get(self.selected) {
+            Some(index) => *index,
+            None => return,
+        };
+        self.message = match action(self, index) {
+            Ok(message) => message,
+            Err(e) => e.to_string(),
+        };
+    }
+
+    /// Handle a key answering the prompt.
+    fn answer(&mut self, code: KeyCode) {
+        match (self.prompt.clone(), code) {
+            (Prompt::Filter, Enter) => self.prompt = Prompt::None,
+            (Prompt::Filter, Esc) => {
+                self.prompt = Prompt::None;
+                self.filter.clear();
+                self.update();
+            }
+            (Prompt::Filter, Backspace) => {
+                self.filter.pop();
+                self.update();
+            }
+            (Prompt::Filter, Char(c)) => {
+                self.filter.push(c);
+                self.update();
+            }
+            (Prompt::Delete, Char('y')) => {
+                self.prompt = Prompt::None;
+                self.act(|b, i| {
+                    notes::trash(&b.notes[i..=i])?;
+                    let note = b.notes.remove(i);
+                    b.update();
+                    Ok(format!("Moved {} to trash.", note.relative_path()))
+                });
+            }
+            (Prompt::Delete, _) => self.prompt = Prompt::None,
+            (Prompt::Move(category), Enter) => {
+                self.prompt = Prompt::None;
+                self.act(|b, i| {
+                    let (moved, _) = notes::move_note(&b.notes[i], &category)?;
+                    b.reload(i, &moved.path)?;
+                    Ok(format!("Moved to {}.", moved.relative_path()))
+                });
+            }
+            (Prompt::Move(_), Esc) => self.prompt = Prompt::None,
+            (Prompt::Move(mut category), Backspace) => {
+                category.pop();
+                self.prompt = Prompt::Move(category);
+            }
+            (Prompt::Move(mut category), Char(c)) => {
+                category.push(c);
+                self.prompt = Prompt::Move(category);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle keys until the browser is closed.
+    fn run<W: Write>(&mut self, w: &mut W) -> Result<()> {
+        queue!(w, Clear(ClearType::All))?;
+        loop {
+            self.write(w)?;
+            let (code, control) = match event::read()? {
+                Event::Key(KeyEvent { code, modifiers }) => {
+                    (code, modifiers.contains(KeyModifiers::CONTROL))
+                }
+                Event::Resize(..) => {
+                    queue!(w, Clear(ClearType::All))?;
+                    self.update_preview();
+                    continue;
+                }
+                _ => continue,
+            };
+            if self.prompt != Prompt::None {
+                self.answer(code);
+                continue;
+            }
+            self.message.clear();
+            let page = i64::from(Browser::layout()?.1.height);
+            match (code, control) {
+                (Char('c'), true) | (Char('q'), false) | (Esc, _) => return Ok(()),
+                (Char('d'), true) => {
+                    if let Some(view) = &mut self.preview {
+                        view.try_scroll_lines(page as i32 / 2);
+                    }
+                }
+                (Char('u'), true) => {
+                    if let Some(view) = &mut self.preview {
+                        view.try_scroll_lines(-page as i32 / 2);
+                    }
+                }
+                (_, true) => {}
+                (Down, _) | (Char('j'), _) => self.select(1),
+                (Up, _) | (Char('k'), _) => self.select(-1),
+                (PageDown, _) => self.select(page),
+                (PageUp, _) => self.select(-page),
+                (Home, _) | (Char('g'), _) => self.select(-(self.shown.len() as i64)),
+                (End, _) | (Char('G'), _) => self.select(self.shown.len() as i64),
+                (Char('/'), _) => self.prompt = Prompt::Filter,
+                (Char('s'), _) => {
+                    self.sort = match self.sort {
+                        Sort::Date => Sort::Updated,
+                        Sort::Updated => Sort::Title,
+                        Sort::Title => Sort::Date,
+                    };
+                    self.update();
+                }
+                (Enter, _) => {
+                    if let Some(note) = self.current().cloned() {
+                        let page = Page::from_notes(std::slice::from_ref(&note));
+                        show::view(w, self.skin.clone(), page)?;
+                        queue!(w, Clear(ClearType::All))?;
+                        self.act(|b, i| b.reload(i, &note.path).map(|_| String::new()));
+                    }
+                }
+                (Char('e'), _) => {
+                    if let Some(note) = self.current().cloned() {
+                        let edited = show::suspend(w, || notes::edit_note(&note));
+                        self.act(|b, i| {
+                            let changed = edited?;
+                            b.reload(i, &note.path)?;
+                            Ok(match changed {
+                                true => format!("Edited {}.", note.relative_path()),
+                                false => format!("No changes to {}.", note.relative_path()),
+                            })
+                        });
+                    }
+                }
+                (Char('d'), _) if self.current().is_some() => self.prompt = Prompt::Delete,
+                (Char('m'), _) => {
+                    if let Some(note) = self.current() {
+                        self.prompt = Prompt::Move(note.category.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Browse `notes` in a two-pane raw terminal: the list on the left, the
+/// selected note on the right.
+pub fn run(skin: MadSkin, notes: Vec<Note>) -> Result<()> {
+    show::in_terminal(|w| Browser::new(skin, notes).run(w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_test() {
+        let note = |category: &str, title: &str, date: &str| {
+            Note::sample(
+                category,
+                title,
+                &format!("title: {}\ndate: {}", title, date),
+            )
+        };
+        let notes = vec![
+            note("work", "plan", "2021-05-01"),
+            note("home", "todo", "2021-05-01"),
+            note("work", "budget", "2021-04-01"),
+            note("work", "standup", "2021-06-01"),
+        ];
+        assert_eq!(list(&notes, "", Sort::Date), vec![1, 3, 0, 2]);
+        assert_eq!(list(&notes, "", Sort::Title), vec![1, 2, 0, 3]);
+        assert_eq!(list(&notes, "plan", Sort::Title), vec![0]);
+        let shown = list(&notes, "", Sort::Title);
+        assert_eq!(
+            rows(&notes, &shown),
+            vec![
+                Row::Category("home".to_owned()),
+                Row::Note(0),
+                Row::Category("work".to_owned()),
+                Row::Note(1),
+                Row::Note(2),
+                Row::Note(3),
+            ]
+        );
+    }
+}
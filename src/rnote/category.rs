@@ -0,0 +1,264 @@
+use crate::rnote::{
+    config, git, links,
+    note::Note,
+    notes::{self, check_category, get_all_notes, get_base_path},
+    trash,
+};
+use anyhow::{anyhow, Result};
+use dialoguer::{theme::ColorfulTheme, Select};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A category with the number of notes in it and in its subcategories.
+#[derive(Debug, Default)]
+struct Node {
+    count: usize,
+    children: BTreeMap<String, Node>,
+}
+
+/// Build the tree of categories from the category of every note.
+fn tree(categories: &[String]) -> Node {
+    let mut root = Node::default();
+    for category in categories {
+        root.count += 1;
+        let mut node = &mut root;
+        for part in category.split('/').filter(|p| !p.is_empty()) {
+            node = node.children.entry(part.to_owned()).or_default();
+            node.count += 1;
+        }
+    }
+    root
+}
+
+/// Render the subcategories of `node` below it, each line starting with
+/// `prefix`.
+fn render(node: &Node, prefix: &str, out: &mut String) {
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let (branch, indent) = match i + 1 == node.children.len() {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        out.push_str(&format!("{}{}{} ({})\n", prefix, branch, name, child.count));
+        render(child, &format!("{}{}", prefix, indent), out);
+    }
+}
+
+/// Render the tree of categories, counting the notes of subcategories too.
+fn render_tree(root: &Node) -> String {
+    let mut out = String::new();
+    let nested: usize = root.children.values().map(|c| c.count).sum();
+    if root.count > nested {
+        out.push_str(&format!("(no category) ({})\n", root.count - nested));
+    }
+    for (name, child) in &root.children {
+        out.push_str(&format!("{} ({})\n", name, child.count));
+        render(child, "", &mut out);
+    }
+    out
+}
+
+/// Print every category as a tree with its number of notes.
+pub fn list() -> Result<()> {
+    let categories: Vec<String> = get_all_notes()?.into_iter().map(|n| n.category).collect();
+    print!("{}", render_tree(&tree(&categories)));
+    Ok(())
+}
+
+/// Get the notes of `category` and of its subcategories.
+fn notes_in(category: &str) -> Result<Vec<Note>> {
+    let nested = format!("{}/", category);
+    Ok(get_all_notes()?
+        .into_iter()
+        .filter(|n| n.category == category || n.category.starts_with(&nested))
+        .collect())
+}
+
+/// Check that the category `src` can be moved into `dst`, returning both
+/// checked and the directory of `src`.
+fn check_pair(src: &str, dst: &str) -> Result<(String, String, PathBuf)> {
+    let (src, dst) = (check_category(src)?, check_category(dst)?);
+    if dst == src || dst.starts_with(&format!("{}/", src)) {
+        return Err(anyhow!("Can't move {} into itself.", src));
+    }
+    let dir = PathBuf::from(get_base_path()?).join(&src);
+    if !dir.is_dir() {
+        return Err(anyhow!("No category \"{}\".", src));
+    }
+    Ok((src, dst, dir))
+}
+
+/// Rename the category `old` to `new`, with its subcategories, and update the
+/// links to its notes.
+pub fn rename(old: &str, new: &str) -> Result<()> {
+    let (old, new, from) = check_pair(old, new)?;
+    let base = PathBuf::from(get_base_path()?);
+    let to = base.join(&new);
+    if to.exists() {
+        return Err(anyhow!(
+            "Category \"{}\" already exists. Use `rnote category merge {} {}`.",
+            new,
+            old,
+            new
+        ));
+    }
+    // Work out where every note goes before touching the tree, so that a
+    // failure leaves it as it was.
+    let mut moves = Vec::new();
+    for note in notes_in(&old)? {
+        let mut moved = note.clone();
+        moved.path = to.join(note.path.strip_prefix(&from)?);
+        moved.category = format!("{}{}", new, &note.category[old.len()..]);
+        moves.push((note, moved));
+    }
+    if let Some((parent, _)) = new.rsplit_once('/') {
+        notes::create_dir(parent)?;
+    }
+    fs::rename(&from, &to)?;
+    let relinked = links::relink(&moves)?;
+    notes::remove_empty_dirs()?;
+    git::commit(&format!("Rename category {} to {}", old, new))?;
+    println!(
+        "Renamed category {} to {} with {} note(s).",
+        old,
+        new,
+        moves.len()
+    );
+    if relinked > 0 {
+        println!("Updated links in {} note(s).", relinked);
+    }
+    Ok(())
+}
+
+/// What to do with a note whose name is taken in the category it goes to.
+enum Collision {
+    Suffix,
+    Skip,
+    Replace,
+}
+
+/// Ask what to do with `note` as `path` is taken.
+fn ask(note: &Note, path: &Path) -> Result<Collision> {
+    let choice = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("{} already exists", path.display()))
+        .items(&[
+            format!("Keep both, adding a suffix to {}", note.relative_path()),
+            format!("Skip {}", note.relative_path()),
+            "Replace it, moving it to the trash".to_owned(),
+        ])
+        .default(0)
+        .interact()?;
+    Ok(match choice {
+        0 => Collision::Suffix,
+        1 => Collision::Skip,
+        _ => Collision::Replace,
+    })
+}
+
+/// Get a free path in `dir` for the note `name`, adding `-2`, `-3`... to it.
+fn with_suffix(dir: &Path, name: &str) -> PathBuf {
+    let extension = &config::get().extension;
+    (2..)
+        .map(|i| dir.join(format!("{}-{}.{}", name, i, extension)))
+        .find(|p| !p.exists())
+        .unwrap_or_default()
+}
+
+/// Move every note of the category `src` to `dst`, keeping subcategories.
+/// Notes whose name is taken get a suffix if `suffix` is set, else the user is
+/// asked what to do.
+pub fn merge(src: &str, dst: &str, suffix: bool) -> Result<()> {
+    let (src, dst, _) = check_pair(src, dst)?;
+    let base = PathBuf::from(get_base_path()?);
+    let mut moves = Vec::new();
+    let mut skipped = 0;
+    for note in notes_in(&src)? {
+        let category = format!("{}{}", dst, &note.category[src.len()..]);
+        let dir = base.join(&category);
+        let mut path = dir.join(note.path.file_name().unwrap_or_default());
+        if path.exists() {
+            let collision = match suffix {
+                true => Collision::Suffix,
+                false => ask(&note, &path)?,
+            };
+            match collision {
+                Collision::Suffix => path = with_suffix(&dir, &note.name()),
+                Collision::Skip => {
+                    skipped += 1;
+                    continue;
+                }
+                Collision::Replace => {
+                    trash::trash_note(&path)?;
+                }
+            }
+        }
+        notes::create_dir(&category)?;
+        let moved = notes::move_file(&note, &path)?;
+        moves.push((note, moved));
+    }
+    let relinked = links::relink(&moves)?;
+    notes::remove_empty_dirs()?;
+    git::commit(&format!("Merge category {} into {}", src, dst))?;
+    println!("Moved {} note(s) from {} to {}.", moves.len(), src, dst);
+    if skipped > 0 {
+        println!("Skipped {} note(s), left in {}.", skipped, src);
+    }
+    if relinked > 0 {
+        println!("Updated links in {} note(s).", relinked);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_pair_test() {
+        let error = |src, dst| check_pair(src, dst).unwrap_err().to_string();
+        assert_eq!(error("work", "work/"), "Can't move work into itself.");
+        assert_eq!(
+            error("work", "work/archive"),
+            "Can't move work into itself."
+        );
+        assert!(error("work", "../work").starts_with("Invalid category"));
+        assert!(error("", "work").starts_with("Invalid category"));
+        assert!(error("test_no_such_category", "other").starts_with("No category"));
+    }
+
+    #[test]
+    fn with_suffix_test() {
+        let dir = std::env::temp_dir().join(format!("rnote-suffix-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let extension = &config::get().extension;
+        assert_eq!(
+            with_suffix(&dir, "plan"),
+            dir.join(format!("plan-2.{}", extension))
+        );
+        fs::write(dir.join(format!("plan-2.{}", extension)), "").unwrap();
+        assert_eq!(
+            with_suffix(&dir, "plan"),
+            dir.join(format!("plan-3.{}", extension))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tree_test() {
+        let categories: Vec<String> = ["", "home", "work", "work/projects/rnote", "work/archive"]
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        let lines = [
+            "(no category) (1)",
+            "home (1)",
+            "work (3)",
+            "├── archive (1)",
+            "└── projects (1)",
+            "    └── rnote (1)",
+        ];
+        assert_eq!(render_tree(&tree(&categories)), lines.join("\n") + "\n");
+    }
+}
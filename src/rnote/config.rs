@@ -0,0 +1,582 @@
+use crate::rnote::output::Format;
+use anyhow::{anyhow, Result};
+use chrono::format::{Item, StrftimeItems};
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+use toml::{value::Table, Value};
+
+/// Every key that can be set, in the order they are listed.
+pub const KEYS: [&str; 20] = [
+    "notes_dir",
+    "default_notebook",
+    "editor",
+    "fallback_editors",
+    "default_category",
+    "templates_dir",
+    "journal_category",
+    "journal_template",
+    "extension",
+    "date_format",
+    "display_date_format",
+    "display_time_format",
+    "category_date_format",
+    "count_revisions",
+    "output_format",
+    "skin.headers",
+    "skin.bold",
+    "skin.italic",
+    "skin.strikeout",
+    "skin.scrollbar",
+];
+
+/// Keys holding a list, given as comma separated values.
+const LIST_KEYS: [&str; 1] = ["fallback_editors"];
+
+/// Keys holding `true` or `false`.
+const BOOL_KEYS: [&str; 1] = ["count_revisions"];
+
+/// Settings of rnote, from defaults, the config file, `RNOTE_*` environment
+/// variables and command line flags, each overriding the previous one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Root directory of all notes, `$XDG_DATA_HOME/rnote` if empty.
+    pub notes_dir: String,
+    /// Notebook used instead of `notes_dir`, if any.
+    pub default_notebook: String,
+    /// Root directory of each notebook by name.
+    pub notebooks: BTreeMap<String, String>,
+    /// Command used to edit notes, `$VISUAL` or `$EDITOR` if empty.
+    pub editor: String,
+    /// Editors tried in order when none is set.
+    pub fallback_editors: Vec<String>,
+    /// Category of new notes, today's date if empty.
+    pub default_category: String,
+    /// Directory of note templates, `$XDG_CONFIG_HOME/rnote/templates` if
+    /// empty.
+    pub templates_dir: String,
+    /// Category of journal entries.
+    pub journal_category: String,
+    /// Template of new journal entries, used if it exists.
+    pub journal_template: String,
+    /// Extension of note files.
+    pub extension: String,
+    /// Format of the `{{date}}` placeholder of templates, also accepted in
+    /// headers. Headers are written with ISO 8601 timestamps.
+    pub date_format: String,
+    /// Format of dates shown to the user.
+    pub display_date_format: String,
+    /// Format of dates with time shown to the user, e.g. in the viewer.
+    pub display_time_format: String,
+    /// Format of the date used as category when none is given.
+    pub category_date_format: String,
+    /// Count the edits of every note in a `revisions` field of its header.
+    pub count_revisions: bool,
+    /// Output format of `list`, `search` and `tags`, prompting if empty.
+    pub output_format: String,
+    pub skin: Skin,
+}
+
+/// Colors of the note viewer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Skin {
+    pub headers: String,
+    pub bold: String,
+    pub italic: String,
+    pub strikeout: String,
+    pub scrollbar: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            notes_dir: String::new(),
+            default_notebook: String::new(),
+            notebooks: BTreeMap::new(),
+            editor: String::new(),
+            fallback_editors: vec![
+                "nvim".to_owned(),
+                "vim".to_owned(),
+                "vi".to_owned(),
+                "nano".to_owned(),
+            ],
+            default_category: String::new(),
+            templates_dir: String::new(),
+            journal_category: "journal".to_owned(),
+            journal_template: "journal".to_owned(),
+            extension: "md".to_owned(),
+            date_format: "%d-%m-%Y".to_owned(),
+            display_date_format: "%Y-%m-%d".to_owned(),
+            display_time_format: "%Y-%m-%d %H:%M".to_owned(),
+            category_date_format: "%Y-%m-%d".to_owned(),
+            count_revisions: false,
+            output_format: String::new(),
+            skin: Skin::default(),
+        }
+    }
+}
+
+impl Default for Skin {
+    fn default() -> Self {
+        Skin {
+            headers: "rgb(231, 195, 115)".to_owned(),
+            bold: "green".to_owned(),
+            italic: "blue".to_owned(),
+            strikeout: "red".to_owned(),
+            scrollbar: "178".to_owned(),
+        }
+    }
+}
+
+impl Config {
+    /// Root directory of all notes, of the selected notebook if any, empty if
+    /// it cannot be found.
+    pub fn notes_dir(&self) -> String {
+        if let Some(dir) = self.notebooks.get(&self.default_notebook) {
+            return expand_home(dir);
+        }
+        match self.notes_dir.is_empty() {
+            true => xdg_dir("XDG_DATA_HOME", ".local/share")
+                .map(|d| d.join("rnote").to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            false => expand_home(&self.notes_dir),
+        }
+    }
+
+    /// Command used to edit notes: the configured one, `$VISUAL`, `$EDITOR` or
+    /// the first fallback editor found in `$PATH`. Empty if there is none.
+    pub fn editor(&self) -> String {
+        if !self.editor.is_empty() {
+            return self.editor.clone();
+        }
+        for var in ["VISUAL", "EDITOR"] {
+            match env::var(var) {
+                Ok(editor) if !editor.trim().is_empty() => return editor,
+                _ => {}
+            }
+        }
+        self.fallback_editors
+            .iter()
+            .find(|e| e.split_whitespace().next().map(is_in_path).unwrap_or(false))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Directory of note templates.
+    pub fn templates_dir(&self) -> Result<String> {
+        match self.templates_dir.is_empty() {
+            true => Ok(path()?
+                .with_file_name("templates")
+                .to_string_lossy()
+                .into_owned()),
+            false => Ok(expand_home(&self.templates_dir)),
+        }
+    }
+
+    /// Output format used when none is given on the command line.
+    pub fn output_format(&self) -> Option<Format> {
+        self.output_format.parse().ok()
+    }
+
+    /// Value of `key`, with defaults resolved.
+    pub fn value(&self, key: &str) -> Result<String> {
+        match key {
+            "notes_dir" => return Ok(self.notes_dir()),
+            "editor" => return Ok(self.editor()),
+            "templates_dir" => return self.templates_dir(),
+            _ => {}
+        }
+        check_key(key)?;
+        let mut value = &Value::try_from(self)?;
+        for part in key.split('.') {
+            value = value
+                .get(part)
+                .ok_or_else(|| anyhow!("Unknown key \"{}\".", key))?;
+        }
+        match value {
+            Value::Array(items) => Ok(items
+                .iter()
+                .filter_map(|v| v.as_str())
+                .collect::<Vec<&str>>()
+                .join(", ")),
+            Value::Boolean(b) => Ok(b.to_string()),
+            _ => Ok(value.as_str().unwrap_or_default().to_owned()),
+        }
+    }
+
+    /// Check that every value can be used.
+    fn validate(&self) -> Result<()> {
+        if !self.default_notebook.is_empty() && !self.notebooks.contains_key(&self.default_notebook)
+        {
+            return Err(anyhow!(
+                "Unknown notebook \"{}\". Add it with `rnote notebook add`.",
+                self.default_notebook
+            ));
+        }
+        if !self.output_format.is_empty() {
+            self.output_format.parse::<Format>()?;
+        }
+        for format in [
+            &self.date_format,
+            &self.display_date_format,
+            &self.display_time_format,
+            &self.category_date_format,
+        ] {
+            if format.is_empty() || StrftimeItems::new(format).any(|i| i == Item::Error) {
+                return Err(anyhow!("Invalid date format \"{}\".", format));
+            }
+        }
+        if self.journal_category.is_empty() {
+            return Err(anyhow!("journal_category cannot be empty."));
+        }
+        if self.extension.is_empty() || self.extension.contains('/') {
+            return Err(anyhow!("Invalid extension \"{}\".", self.extension));
+        }
+        for color in [
+            &self.skin.headers,
+            &self.skin.bold,
+            &self.skin.italic,
+            &self.skin.strikeout,
+            &self.skin.scrollbar,
+        ] {
+            parse_color(color)?;
+        }
+        Ok(())
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Get an XDG base directory from `var`, or `$HOME/<fallback>` if it is unset
+/// or not absolute as the specification requires.
+pub fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    match env::var(var) {
+        Ok(dir) if Path::new(&dir).is_absolute() => Some(PathBuf::from(dir)),
+        _ => env::var("HOME")
+            .ok()
+            .filter(|h| !h.is_empty())
+            .map(|h| PathBuf::from(h).join(fallback)),
+    }
+}
+
+/// Check if `program` is an executable file, directly or in `$PATH`.
+fn is_in_path(program: &str) -> bool {
+    let is_executable = |p: &Path| {
+        fs::metadata(p)
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    };
+    if program.contains('/') {
+        return is_executable(Path::new(program));
+    }
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+/// Replace a leading `~` by `$HOME`.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home, rest)
+        }
+        _ => path.to_owned(),
+    }
+}
+
+/// Fail if `key` is not a known key.
+fn check_key(key: &str) -> Result<()> {
+    match KEYS.contains(&key) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Unknown key \"{}\". Known keys: {}.",
+            key,
+            KEYS.join(", ")
+        )),
+    }
+}
+
+/// Name of the environment variable overriding `key`, e.g. `RNOTE_SKIN_BOLD`.
+fn env_name(key: &str) -> String {
+    format!("RNOTE_{}", key.replace('.', "_").to_uppercase())
+}
+
+/// Set the dotted `key` to `value` in `table`.
+fn set_key(table: &mut Table, key: &str, value: &str) -> Result<()> {
+    check_key(key)?;
+    let mut table = table;
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        table = table
+            .entry(part.to_owned())
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| anyhow!("\"{}\" is not a table.", part))?;
+    }
+    if BOOL_KEYS.contains(&key) {
+        let value = value.trim().parse().map_err(|_| {
+            anyhow!(
+                "Invalid value \"{}\" for {}, expected true or false.",
+                value,
+                key
+            )
+        })?;
+        table.insert(last.to_owned(), Value::Boolean(value));
+        return Ok(());
+    }
+    let value = match LIST_KEYS.contains(&key) {
+        true => Value::Array(
+            value
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| Value::String(s.to_owned()))
+                .collect(),
+        ),
+        false => Value::String(value.to_owned()),
+    };
+    table.insert(last.to_owned(), value);
+    Ok(())
+}
+
+/// Build the configuration from the content of the config file, the
+/// environment and command line overrides.
+fn from_layers(
+    mut file: Table,
+    env: impl Fn(&str) -> Option<String>,
+    overrides: &[(String, String)],
+) -> Result<Config> {
+    for key in KEYS.iter() {
+        if let Some(value) = env(&env_name(key)) {
+            set_key(&mut file, key, &value)?;
+        }
+    }
+    for (key, value) in overrides {
+        set_key(&mut file, key, value)?;
+    }
+    let config: Config = Value::Table(file).try_into()?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Get the path of the config file.
+pub fn path() -> Result<PathBuf> {
+    let dir = xdg_dir("XDG_CONFIG_HOME", ".config")
+        .ok_or_else(|| anyhow!("Neither XDG_CONFIG_HOME nor HOME is set."))?;
+    Ok(dir.join("rnote").join("config.toml"))
+}
+
+/// Read the config file, empty if it does not exist.
+pub fn read_file() -> Result<Table> {
+    let path = match path() {
+        Ok(path) => path,
+        Err(_) => return Ok(Table::new()),
+    };
+    if !path.exists() {
+        return Ok(Table::new());
+    }
+    toml::from_str(&fs::read_to_string(&path)?)
+        .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))
+}
+
+/// Load the configuration with `overrides` from the command line.
+pub fn load(overrides: &[(String, String)]) -> Result<Config> {
+    from_layers(read_file()?, |name| env::var(name).ok(), overrides)
+}
+
+/// Load the configuration used for the rest of the run. If `lenient`, an
+/// invalid configuration is reported and the defaults are used instead, so
+/// that it can still be fixed with `rnote config`.
+pub fn init(overrides: &[(String, String)], lenient: bool) -> Result<()> {
+    let config = match load(overrides) {
+        Ok(config) => config,
+        Err(e) if lenient => {
+            eprintln!(
+                "Warning: {}\nUsing the default settings until it is fixed.",
+                e
+            );
+            Config::default()
+        }
+        Err(e) => return Err(e),
+    };
+    CONFIG
+        .set(config)
+        .map_err(|_| anyhow!("Configuration is already loaded."))
+}
+
+/// Get the configuration, loading it without overrides if needed. Exit if it
+/// is invalid rather than going on with other settings than the user's.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| match load(&[]) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    })
+}
+
+/// Print the value of `key`.
+pub fn print(key: &str) -> Result<()> {
+    println!("{}", get().value(key)?);
+    Ok(())
+}
+
+/// Print every key with its value.
+pub fn list() -> Result<()> {
+    let config = get();
+    for key in KEYS.iter() {
+        println!("{} = {}", key, config.value(key)?);
+    }
+    Ok(())
+}
+
+/// Check the content of the config file and write it.
+pub fn write_file(file: &Table) -> Result<()> {
+    from_layers(file.clone(), |_| None, &[])?;
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, toml::to_string_pretty(file)?)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+/// Write `key = value` to the config file.
+pub fn set(key: &str, value: &str) -> Result<()> {
+    let mut file = read_file()?;
+    set_key(&mut file, key, value)?;
+    write_file(&file)
+}
+
+/// Parse a color name (e.g. `green`), an ANSI value (e.g. `178`), `#rrggbb`
+/// or `rgb(r, g, b)`.
+pub fn parse_color(s: &str) -> Result<Color> {
+    let s = s.trim().to_lowercase();
+    let invalid = || anyhow!("Invalid color \"{}\".", s);
+    if let Ok(n) = s.parse::<u8>() {
+        return Ok(Color::AnsiValue(n));
+    }
+    if let Some(hex) = s.strip_prefix('#') {
+        let n = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        return Ok(Color::Rgb {
+            r: (n >> 16) as u8,
+            g: (n >> 8) as u8,
+            b: n as u8,
+        });
+    }
+    if let Some(rgb) = s.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let parts: Vec<u8> = rgb
+            .split(',')
+            .map(|p| p.trim().parse().map_err(|_| invalid()))
+            .collect::<Result<_>>()?;
+        return match parts[..] {
+            [r, g, b] => Ok(Color::Rgb { r, g, b }),
+            _ => Err(invalid()),
+        };
+    }
+    match s.as_str() {
+        "black" => Ok(Color::Black),
+        "red" => Ok(Color::Red),
+        "green" => Ok(Color::Green),
+        "yellow" => Ok(Color::Yellow),
+        "blue" => Ok(Color::Blue),
+        "magenta" => Ok(Color::Magenta),
+        "cyan" => Ok(Color::Cyan),
+        "white" => Ok(Color::White),
+        "grey" | "gray" => Ok(Color::Grey),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_test() {
+        let file: Table = toml::from_str("extension = \"txt\"\n[skin]\nbold = \"red\"").unwrap();
+        let env = |name: &str| match name {
+            "RNOTE_EXTENSION" => Some("org".to_owned()),
+            "RNOTE_DEFAULT_CATEGORY" => Some("inbox".to_owned()),
+            _ => None,
+        };
+        let overrides = vec![
+            ("default_category".to_owned(), "work".to_owned()),
+            ("fallback_editors".to_owned(), "micro, nano".to_owned()),
+        ];
+        let config = from_layers(file, env, &overrides).unwrap();
+        assert_eq!(config.fallback_editors, vec!["micro", "nano"]);
+        assert_eq!(config.extension, "org");
+        assert_eq!(config.default_category, "work");
+        assert_eq!(config.skin.bold, "red");
+        assert_eq!(config.skin.italic, "blue");
+        assert_eq!(config.date_format, "%d-%m-%Y");
+    }
+
+    #[test]
+    fn invalid_test() {
+        let invalid = |key: &str, value: &str| {
+            let overrides = vec![(key.to_owned(), value.to_owned())];
+            from_layers(Table::new(), |_| None, &overrides).is_err()
+        };
+        assert!(invalid("output_format", "xml"));
+        assert!(invalid("skin.bold", "rgb(1, 2)"));
+        assert!(invalid("colour", "red"));
+        assert!(invalid("date_format", "%Q"));
+        assert!(invalid("default_notebook", "nope"));
+        assert!(invalid("count_revisions", "yes"));
+        assert!(!invalid("count_revisions", "true"));
+        assert!(!invalid("output_format", "json"));
+        let file: Table = toml::from_str("unknown = 1").unwrap();
+        assert!(from_layers(file, |_| None, &[]).is_err());
+    }
+
+    #[test]
+    fn notebook_test() {
+        let file: Table =
+            toml::from_str("notes_dir = \"/notes\"\n[notebooks]\nwork = \"/work\"").unwrap();
+        let config = from_layers(file.clone(), |_| None, &[]).unwrap();
+        assert_eq!(config.notes_dir(), "/notes");
+        let overrides = vec![("default_notebook".to_owned(), "work".to_owned())];
+        let config = from_layers(file.clone(), |_| None, &overrides).unwrap();
+        assert_eq!(config.notes_dir(), "/work");
+        let overrides = vec![("default_notebook".to_owned(), "home".to_owned())];
+        assert!(from_layers(file, |_| None, &overrides).is_err());
+    }
+
+    #[test]
+    fn is_in_path_test() {
+        assert!(is_in_path("sh"));
+        assert!(is_in_path("/bin/sh"));
+        assert!(!is_in_path("rnote-no-such-editor"));
+    }
+
+    #[test]
+    fn parse_color_test() {
+        assert_eq!(parse_color("Green").unwrap(), Color::Green);
+        assert_eq!(parse_color("178").unwrap(), Color::AnsiValue(178));
+        assert_eq!(
+            parse_color("#e7c373").unwrap(),
+            Color::Rgb {
+                r: 231,
+                g: 195,
+                b: 115
+            }
+        );
+        assert!(parse_color("rgb(1, 2, 300)").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+/// How destructive commands ask before acting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Confirmation {
+    /// Do not ask, act right away.
+    pub yes: bool,
+    /// Only print what would be done.
+    pub dry_run: bool,
+}
+
+impl Confirmation {
+    /// Print the `items` about to be affected by `action` (e.g. "moved to trash")
+    /// and ask to go on. Return `true` if the action should be carried out.
+    pub fn ask(&self, action: &str, items: &[String]) -> Result<bool> {
+        self.ask_about("note", action, items)
+    }
+
+    /// Like [`Confirmation::ask`], for `items` that are not all notes.
+    pub fn ask_files(&self, action: &str, items: &[String]) -> Result<bool> {
+        self.ask_about("file", action, items)
+    }
+
+    fn ask_about(&self, kind: &str, action: &str, items: &[String]) -> Result<bool> {
+        println!("{} {}(s) will be {}:", items.len(), kind, action);
+        for item in items {
+            println!("  {}", item);
+        }
+        if self.dry_run {
+            println!("Dry run, nothing was done.");
+            return Ok(false);
+        }
+        if self.yes {
+            return Ok(true);
+        }
+        let ok = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Continue with {} {}(s)?", items.len(), kind))
+            .default(false)
+            .interact()?;
+        if !ok {
+            println!("Canceling...");
+        }
+        Ok(ok)
+    }
+}
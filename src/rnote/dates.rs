@@ -0,0 +1,351 @@
+use crate::rnote::{
+    config,
+    confirm::Confirmation,
+    git,
+    note::{split_header, FrontMatter, Note},
+    notes::get_all_notes,
+    output::NotFound,
+};
+use anyhow::{anyhow, Result};
+use chrono::{
+    DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone,
+};
+use std::{fmt, fs};
+
+/// Date formats accepted in the `date` field of a header, besides ISO 8601
+/// timestamps and the configured one.
+const DATE_FORMATS: [&str; 2] = ["%d-%m-%Y", "%Y-%m-%d"];
+
+/// Formats of timestamps without offset accepted in headers, read as local
+/// time.
+const TIME_FORMATS: [&str; 3] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Date and time written in headers, with the offset of where it was written.
+pub type Timestamp = DateTime<FixedOffset>;
+
+/// Get the current time in the local timezone.
+pub fn now() -> Timestamp {
+    Local::now().fixed_offset()
+}
+
+/// Get today's date in the local timezone.
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Get the timestamp of the start of `day` in the local timezone.
+pub fn start_of(day: NaiveDate) -> Timestamp {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    local(midnight)
+}
+
+/// Get the timestamp of a note of `day`: now if it is today, else the start
+/// of it.
+pub fn at(day: NaiveDate) -> Timestamp {
+    match day == today() {
+        true => now(),
+        false => start_of(day),
+    }
+}
+
+/// Read `time` as local time, the earliest one if it happens twice and as UTC
+/// if it is skipped by a change of offset.
+fn local(time: NaiveDateTime) -> Timestamp {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|t| t.fixed_offset())
+        .unwrap_or_else(|| Local.from_utc_datetime(&time).fixed_offset())
+}
+
+/// Parse a date written in one of the supported formats.
+pub fn parse_date(s: &str) -> Option<NaiveDate> {
+    let configured = config::get().date_format.as_str();
+    std::iter::once(&configured)
+        .chain(DATE_FORMATS.iter())
+        .find_map(|f| NaiveDate::parse_from_str(s.trim(), f).ok())
+}
+
+/// Parse the date of a header: an ISO 8601 timestamp, one without offset read
+/// as local time, or a date read as the start of the day.
+pub fn parse_timestamp(s: &str) -> Option<Timestamp> {
+    let s = s.trim();
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .or_else(|| {
+            TIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+                .map(local)
+        })
+        .or_else(|| parse_date(s).map(start_of))
+}
+
+/// Write `time` as an ISO 8601 timestamp, e.g. `2021-05-03T23:30:00+02:00`.
+pub fn format_timestamp(time: &Timestamp) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Parse a duration such as `30d`, `2w`, `12h`, `45m` or `10s`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let invalid = || anyhow!("Invalid duration \"{}\", expected e.g. 30d.", s);
+    let n: i64 = n.parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        "s" => Duration::try_seconds(n),
+        "m" => Duration::try_minutes(n),
+        "h" => Duration::try_hours(n),
+        "d" | "" => Duration::try_days(n),
+        "w" => Duration::try_weeks(n),
+        _ => {
+            return Err(anyhow!(
+                "Invalid duration unit \"{}\", expected one of s, m, h, d, w.",
+                unit
+            ))
+        }
+    };
+    duration.ok_or_else(invalid)
+}
+
+/// Parse a day given as a date, e.g. `2021-05-03`, as `today` or `yesterday`,
+/// or as a duration before `today`, e.g. `2w`.
+pub fn parse_day(s: &str, today: NaiveDate) -> Result<NaiveDate> {
+    match s.trim() {
+        "today" => return Ok(today),
+        "yesterday" => return Ok(today - Duration::days(1)),
+        _ => {}
+    }
+    if let Some(date) = parse_date(s) {
+        return Ok(date);
+    }
+    parse_duration(s)
+        .ok()
+        .and_then(|d| today.checked_sub_signed(Duration::days(d.num_days())))
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid date \"{}\", expected e.g. 2021-05-03, today or 2w.",
+                s
+            )
+        })
+}
+
+/// Days between two optional bounds, both included.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DateRange {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// Range from the days `date`, `since` and `until` given as arguments,
+    /// `date` being a single day.
+    pub fn parse(date: Option<&str>, since: Option<&str>, until: Option<&str>) -> Result<Self> {
+        let day = |s: Option<&str>| s.map(|s| parse_day(s, today())).transpose();
+        let date = day(date)?;
+        let range = DateRange {
+            since: day(since)?.or(date),
+            until: day(until)?.or(date),
+        };
+        if let (Some(since), Some(until)) = (range.since, range.until) {
+            if since > until {
+                return Err(anyhow!("Nothing between {} and {}.", since, until));
+            }
+        }
+        Ok(range)
+    }
+
+    /// Check if no bound is set.
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Check if `date` is in the range. A missing date is only in an
+    /// unbounded range.
+    pub fn contains(&self, date: Option<NaiveDate>) -> bool {
+        match date {
+            Some(date) => {
+                self.since.map(|s| s <= date).unwrap_or(true)
+                    && self.until.map(|u| date <= u).unwrap_or(true)
+            }
+            None => self.is_unbounded(),
+        }
+    }
+
+    /// Keep the `notes` dated in the range, by the date of their header.
+    pub fn filter(&self, notes: Vec<Note>) -> Result<Vec<Note>> {
+        if self.is_unbounded() {
+            return Ok(notes);
+        }
+        let notes: Vec<Note> = notes
+            .into_iter()
+            .filter(|n| self.contains(n.front.date.map(|d| d.date_naive())))
+            .collect();
+        match notes.is_empty() {
+            true => Err(NotFound(format!("No notes dated {}.", self)).into()),
+            false => Ok(notes),
+        }
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.since, self.until) {
+            (Some(since), Some(until)) if since == until => write!(f, "{}", since),
+            (Some(since), Some(until)) => write!(f, "from {} to {}", since, until),
+            (Some(since), None) => write!(f, "since {}", since),
+            (None, Some(until)) => write!(f, "until {}", until),
+            (None, None) => write!(f, "any day"),
+        }
+    }
+}
+
+/// What `migrate` does with a note.
+#[derive(Debug, PartialEq)]
+enum Migration {
+    /// Nothing to do: no header, no dates, or dates written as timestamps.
+    Keep,
+    /// Write the note again with this content.
+    Rewrite(String),
+    /// A date can't be read, so the note is left for the user to fix.
+    Unreadable,
+}
+
+/// Find what `migrate` does with the note `content`.
+fn migration(content: &str) -> Result<Migration> {
+    let (header, body) = match split_header(content) {
+        (Some(header), body) => (header, body),
+        (None, _) => return Ok(Migration::Keep),
+    };
+    let front = FrontMatter::parse(header);
+    // Dates that can't be read are kept as they are with the other fields.
+    if front.extra.contains_key("date") || front.extra.contains_key("updated") {
+        return Ok(Migration::Unreadable);
+    }
+    let stale = |time: Option<Timestamp>| {
+        time.map(|t| !header.contains(&format_timestamp(&t)))
+            .unwrap_or(false)
+    };
+    match stale(front.date) || stale(front.updated) {
+        true => Ok(Migration::Rewrite(front.to_yaml()? + body)),
+        false => Ok(Migration::Keep),
+    }
+}
+
+/// Rewrite the date of every header not written as an ISO 8601 timestamp.
+/// Notes with dates that can't be read are listed and left as they are.
+pub fn migrate(confirmation: &Confirmation) -> Result<()> {
+    let mut changes = Vec::new();
+    let mut unreadable = Vec::new();
+    for note in get_all_notes()? {
+        match migration(&fs::read_to_string(&note.path)?)? {
+            Migration::Keep => {}
+            Migration::Rewrite(content) => changes.push((note, content)),
+            Migration::Unreadable => unreadable.push(note.relative_path()),
+        }
+    }
+    if changes.is_empty() && unreadable.is_empty() {
+        println!("All dates are already ISO 8601 timestamps.");
+        return Ok(());
+    }
+    if !changes.is_empty() {
+        let items: Vec<String> = changes.iter().map(|(n, _)| n.relative_path()).collect();
+        if confirmation.ask("rewritten with ISO 8601 dates", &items)? {
+            for (note, content) in &changes {
+                fs::write(&note.path, content)?;
+            }
+            git::commit("Migrate dates to ISO 8601")?;
+            println!("Migrated {} note(s).", changes.len());
+        }
+    }
+    match unreadable.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "{} note(s) have dates that can't be read, fix them by hand:\n  {}",
+            unreadable.len(),
+            unreadable.join("\n  ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_test() {
+        assert_eq!(parse_duration("30d").unwrap(), Duration::days(30));
+        assert_eq!(parse_duration("2w").unwrap(), Duration::weeks(2));
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999w").is_err());
+    }
+
+    #[test]
+    fn parse_day_test() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = day(2021, 5, 17);
+        assert_eq!(parse_day("2021-01-31", today).unwrap(), day(2021, 1, 31));
+        assert_eq!(parse_day("2w", today).unwrap(), day(2021, 5, 3));
+        assert_eq!(parse_day("yesterday", today).unwrap(), day(2021, 5, 16));
+        assert!(parse_day("soon", today).is_err());
+        assert!(parse_day("100000000d", today).is_err());
+    }
+
+    #[test]
+    fn timestamp_test() {
+        let late = parse_timestamp("2021-05-03T23:30:00+02:00").unwrap();
+        assert_eq!(
+            late.date_naive(),
+            NaiveDate::from_ymd_opt(2021, 5, 3).unwrap()
+        );
+        assert_eq!(format_timestamp(&late), "2021-05-03T23:30:00+02:00");
+        let day = parse_timestamp("03-05-2021").unwrap();
+        assert_eq!(day, start_of(NaiveDate::from_ymd_opt(2021, 5, 3).unwrap()));
+        assert_eq!(
+            parse_timestamp("2021-05-03 08:15"),
+            Some(day + Duration::minutes(495))
+        );
+        assert_eq!(parse_timestamp("soon"), None);
+    }
+
+    #[test]
+    fn migration_test() {
+        let rewritten = match migration("---\ntitle: a\ndate: 03-05-2021\n---\nbody\n").unwrap() {
+            Migration::Rewrite(content) => content,
+            other => panic!("expected a rewrite, got {:?}", other),
+        };
+        let midnight = format_timestamp(&start_of(NaiveDate::from_ymd_opt(2021, 5, 3).unwrap()));
+        assert!(rewritten.contains(&midnight));
+        assert!(rewritten.ends_with("---\nbody\n"));
+        assert_eq!(migration(&rewritten).unwrap(), Migration::Keep);
+        assert_eq!(
+            migration("---\ntitle: a\ndate: someday\n---\n").unwrap(),
+            Migration::Unreadable
+        );
+        assert_eq!(migration("no header").unwrap(), Migration::Keep);
+        assert_eq!(migration("---\ntitle: a\n---\n").unwrap(), Migration::Keep);
+    }
+
+    #[test]
+    fn range_test() {
+        let day = |d| NaiveDate::from_ymd_opt(2021, 3, d);
+        let range = DateRange {
+            since: day(1),
+            until: day(31),
+        };
+        assert!(range.contains(day(1)));
+        assert!(range.contains(day(31)));
+        assert!(!range.contains(NaiveDate::from_ymd_opt(2021, 4, 1)));
+        assert!(!range.contains(None));
+        assert!(DateRange::default().contains(None));
+        assert!(DateRange::parse(None, Some("2021-03-31"), Some("2021-03-01")).is_err());
+        assert_eq!(
+            DateRange::parse(Some("2021-03-01"), None, None)
+                .unwrap()
+                .to_string(),
+            "2021-03-01"
+        );
+    }
+}
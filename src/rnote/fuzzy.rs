@@ -0,0 +1,91 @@
+/// Score how well `candidate` matches `query`, ignoring case.
+/// Higher is better, `None` means no match at all.
+pub fn score(query: &str, candidate: &str) -> Option<u32> {
+    let query = query.to_lowercase();
+    let candidate = candidate.to_lowercase();
+    let q: Vec<char> = query.chars().collect();
+    let c: Vec<char> = candidate.chars().collect();
+    if q.is_empty() {
+        return None;
+    }
+    let extra = c.len().saturating_sub(q.len()) as u32;
+    if query == candidate {
+        return Some(1000);
+    }
+    if candidate.starts_with(&query) {
+        return Some(800u32.saturating_sub(extra).max(601));
+    }
+    if candidate.contains(&query) {
+        return Some(600u32.saturating_sub(extra).max(401));
+    }
+    if let Some(gaps) = subsequence_gaps(&q, &c) {
+        return Some(400u32.saturating_sub(gaps * 10 + extra).max(201));
+    }
+    let distance = levenshtein(&q, &c) as u32;
+    let allowed = (q.len() as u32 / 3).clamp(1, 9);
+    if distance <= allowed {
+        return Some(200 - distance * 20);
+    }
+    None
+}
+
+/// If `q` is a subsequence of `c`, return the number of skipped characters
+/// between the first and the last matched ones.
+fn subsequence_gaps(q: &[char], c: &[char]) -> Option<u32> {
+    let mut qi = 0;
+    let mut first: Option<usize> = None;
+    let mut last = 0;
+    for (ci, ch) in c.iter().enumerate() {
+        if qi < q.len() && *ch == q[qi] {
+            first.get_or_insert(ci);
+            last = ci;
+            qi += 1;
+        }
+    }
+    match qi == q.len() {
+        true => Some((last + 1 - first.unwrap_or(0) - q.len()) as u32),
+        false => None,
+    }
+}
+
+/// Edit distance between `a` and `b`.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein_test() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        assert_eq!(levenshtein(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(levenshtein(&chars(""), &chars("abc")), 3);
+    }
+
+    #[test]
+    fn score_test() {
+        assert_eq!(score("Todo", "todo"), Some(1000));
+        assert!(score("to", "todo") > score("do", "todo"));
+        assert!(score("do", "todo") > score("tdo", "todo"));
+        assert!(score("tdo", "todo").is_some());
+        assert!(score("tido", "todo").is_some());
+        assert!(score("groceries", "todo").is_none());
+        let long = "abcdefghijklmnopqrstuvwxyz0123456789";
+        assert!(score(long, "abcdefghijklmnopqrstuvwxyzXXXXXXXXXXXX").is_none());
+        assert_eq!(
+            score(long, "abcdefghijklmnopqrstuvwxyz0XXXXXXXXX"),
+            Some(20)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use crate::rnote::{note::Note, notes::get_base_path};
+use anyhow::{anyhow, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// Files of the notes directory that are not versioned.
+const GITIGNORE: &str = ".index.json\n.trash/\n";
+
+/// Whether git has an identity to commit with, checked once per run.
+static HAS_IDENTITY: OnceLock<bool> = OnceLock::new();
+
+/// Get the notes directory.
+fn base() -> Result<PathBuf> {
+    Ok(PathBuf::from(get_base_path()?))
+}
+
+/// Check if the notes directory is a git repository.
+pub fn is_enabled() -> Result<bool> {
+    Ok(base()?.join(".git").exists())
+}
+
+/// Build a git command run in `base`.
+fn git_command(base: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(base);
+    // Commits must not fail because no identity is configured.
+    let has_identity = *HAS_IDENTITY.get_or_init(|| {
+        Command::new("git")
+            .arg("-C")
+            .arg(base)
+            .args(["config", "user.email"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    });
+    if !has_identity {
+        cmd.args(["-c", "user.name=rnote", "-c", "user.email=rnote@localhost"]);
+    }
+    cmd.args(args);
+    cmd
+}
+
+/// Run git in `base` and return its output.
+fn git(base: &Path, args: &[&str]) -> Result<String> {
+    let output = git_command(base, args)
+        .output()
+        .map_err(|e| anyhow!("Could not run git: {}", e))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run git in `base`, letting it write to the terminal.
+fn git_interactive(base: &Path, args: &[&str]) -> Result<()> {
+    let status = git_command(base, args)
+        .status()
+        .map_err(|e| anyhow!("Could not run git: {}", e))?;
+    if !status.success() {
+        return Err(anyhow!("git {} failed.", args.join(" ")));
+    }
+    Ok(())
+}
+
+/// Fail if versioning is not enabled.
+fn ensure_enabled() -> Result<()> {
+    match is_enabled()? {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Versioning is not enabled. Enable it with `rnote git init`."
+        )),
+    }
+}
+
+/// Resolve the revision `rev` given by the user to a commit, so that it can
+/// never be read as an option by git.
+fn resolve(base: &Path, rev: &str) -> Result<String> {
+    let commit = format!("{}^{{commit}}", rev);
+    let hash = match rev.starts_with('-') {
+        true => None,
+        false => git(
+            base,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                "--end-of-options",
+                &commit,
+            ],
+        )
+        .ok(),
+    };
+    hash.map(|h| h.trim().to_owned())
+        .ok_or_else(|| anyhow!("Unknown revision \"{}\".", rev))
+}
+
+/// Turn `base` into a git repository and commit every note.
+fn init_in(base: &Path) -> Result<()> {
+    fs::create_dir_all(base)?;
+    git(base, &["init", "--quiet"])?;
+    fs::write(base.join(".gitignore"), GITIGNORE)?;
+    commit_in(base, "Start versioning notes")
+}
+
+/// Turn the notes directory into a git repository and commit every note.
+pub fn init() -> Result<()> {
+    if is_enabled()? {
+        return Err(anyhow!("Versioning is already enabled."));
+    }
+    let base = base()?;
+    init_in(&base)?;
+    println!("Versioning enabled in {}.", base.display());
+    Ok(())
+}
+
+/// Commit every change in `base` with `message`, if anything changed.
+fn commit_in(base: &Path, message: &str) -> Result<()> {
+    git(base, &["add", "--all"])?;
+    if git(base, &["status", "--porcelain"])?.trim().is_empty() {
+        return Ok(());
+    }
+    git(base, &["commit", "--quiet", "-m", message])?;
+    Ok(())
+}
+
+/// Commit every change in the notes directory with `message`, if versioning
+/// is enabled and anything changed.
+pub fn commit(message: &str) -> Result<()> {
+    match is_enabled()? {
+        true => commit_in(&base()?, message),
+        false => Ok(()),
+    }
+}
+
+/// Get the commits of `base` that changed the file at `path`, newest first.
+fn log(base: &Path, path: &str) -> Result<String> {
+    git(
+        base,
+        &[
+            "log",
+            "--follow",
+            "--date=format:%Y-%m-%d %H:%M",
+            "--format=%h  %ad  %s",
+            "--",
+            path,
+        ],
+    )
+}
+
+/// Print the commits that changed `note`.
+pub fn history(note: &Note) -> Result<()> {
+    ensure_enabled()?;
+    let path = note.relative_path();
+    let log = log(&base()?, &path)?;
+    if log.trim().is_empty() {
+        return Err(anyhow!("No history for {}.", path));
+    }
+    print!("{}", log);
+    Ok(())
+}
+
+/// Show the changes of `note` since `rev`, or its last change if no revision
+/// is given.
+pub fn diff(note: &Note, rev: Option<&str>) -> Result<()> {
+    ensure_enabled()?;
+    let base = base()?;
+    let path = note.relative_path();
+    match rev {
+        Some(rev) => git_interactive(&base, &["diff", &resolve(&base, rev)?, "--", &path]),
+        None => git_interactive(
+            &base,
+            &[
+                "log",
+                "-p",
+                "-1",
+                "--follow",
+                "--format=%h  %s",
+                "--",
+                &path,
+            ],
+        ),
+    }
+}
+
+/// Bring `note` back to its content at `rev` and commit it.
+pub fn restore(note: &Note, rev: &str) -> Result<()> {
+    ensure_enabled()?;
+    let base = base()?;
+    let path = note.relative_path();
+    let commit = resolve(&base, rev)?;
+    let content = git(&base, &["show", &format!("{}:{}", commit, path)])?;
+    fs::write(&note.path, content)?;
+    commit_in(&base, &format!("Restore {} to {}", path, rev))?;
+    println!("Restored {} to {}.", path, rev);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_commit_history_test() {
+        let base = std::env::temp_dir().join(format!("rnote-git-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("todo.md"), "first\n").unwrap();
+        init_in(&base).unwrap();
+        fs::write(base.join("todo.md"), "second\n").unwrap();
+        commit_in(&base, "Edit todo.md").unwrap();
+        commit_in(&base, "Nothing changed").unwrap();
+        let log = log(&base, "todo.md").unwrap();
+        let subjects: Vec<&str> = log
+            .lines()
+            .map(|l| l.rsplit("  ").next().unwrap())
+            .collect();
+        assert_eq!(subjects, vec!["Edit todo.md", "Start versioning notes"]);
+        assert!(resolve(&base, "HEAD~1").is_ok());
+        assert!(resolve(&base, "--output=/tmp/x").is_err());
+        assert!(resolve(&base, "nope").is_err());
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use crossterm::style::{style, Attribute, Color};
+use regex::{Regex, RegexBuilder};
+
+/// A line of a note matching the searched pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMatch {
+    /// Line number, starting at 1.
+    pub number: usize,
+    pub line: String,
+    /// Byte ranges of every match in `line`.
+    pub spans: Vec<(usize, usize)>,
+}
+
+/// Pattern to look for in notes, built from the `search` flags.
+pub struct Matcher {
+    regex: Regex,
+    whole_word: bool,
+}
+
+/// Check if `c` is part of a word.
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Check that `start..end` of `line` is neither preceded nor followed by a
+/// word character, as `grep -w` does. Unlike `\b`, this also holds for
+/// patterns starting or ending with other characters, e.g. `c++` or `#todo`.
+fn is_whole_word(line: &str, start: usize, end: usize) -> bool {
+    !line[..start].chars().next_back().is_some_and(is_word)
+        && !line[end..].chars().next().is_some_and(is_word)
+}
+
+impl Matcher {
+    /// Build a matcher for `pattern`, taken literally unless `regex` is set.
+    pub fn new(pattern: &str, regex: bool, ignore_case: bool, whole_word: bool) -> Result<Self> {
+        let pattern = match regex {
+            true => pattern.to_owned(),
+            false => regex::escape(pattern),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+        Ok(Matcher { regex, whole_word })
+    }
+
+    /// Find the byte ranges of every match in `line`.
+    fn find_spans(&self, line: &str) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        let mut at = 0;
+        while let Some(m) = self.regex.find_at(line, at) {
+            let found = m.start() < m.end()
+                && (!self.whole_word || is_whole_word(line, m.start(), m.end()));
+            at = match found {
+                true => {
+                    spans.push((m.start(), m.end()));
+                    m.end()
+                }
+                // Look again from the next character, as a later match may
+                // overlap this one.
+                false => match line[m.start()..].chars().next() {
+                    Some(c) => m.start() + c.len_utf8(),
+                    None => break,
+                },
+            };
+        }
+        spans
+    }
+
+    /// Find every line of `content` matching the pattern.
+    pub fn find_lines(&self, content: &str) -> Vec<LineMatch> {
+        content
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let spans = self.find_spans(line);
+                match spans.is_empty() {
+                    true => None,
+                    false => Some(LineMatch {
+                        number: i + 1,
+                        line: line.to_owned(),
+                        spans,
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+impl LineMatch {
+    /// The line with every match highlighted, if `color` is set.
+    pub fn highlighted(&self, color: bool) -> String {
+        if !color {
+            return self.line.clone();
+        }
+        let mut out = String::new();
+        let mut last = 0;
+        for (start, end) in &self.spans {
+            out.push_str(&self.line[last..*start]);
+            let matched = style(&self.line[*start..*end])
+                .with(Color::Red)
+                .attribute(Attribute::Bold);
+            out.push_str(&matched.to_string());
+            last = *end;
+        }
+        out.push_str(&self.line[last..]);
+        out
+    }
+}
+
+/// Format a match grep-style as `path:line:text`.
+pub fn format_match(path: &str, m: &LineMatch, color: bool) -> String {
+    match color {
+        true => format!(
+            "{}:{}:{}",
+            style(path).with(Color::Magenta),
+            style(m.number).with(Color::Green),
+            m.highlighted(true)
+        ),
+        false => format!("{}:{}:{}", path, m.number, m.line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_test() {
+        let m = Matcher::new("a.b", false, false, false).unwrap();
+        let lines = m.find_lines("axb\na.b a.b\nA.B");
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].number, 2);
+        assert_eq!(lines[0].spans, vec![(0, 3), (4, 7)]);
+    }
+
+    #[test]
+    fn flags_test() {
+        let m = Matcher::new("rust", false, true, true).unwrap();
+        let lines = m.find_lines("Rust\nrustacean\nI like RUST.");
+        let numbers: Vec<usize> = lines.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![1, 3]);
+        let m = Matcher::new(r"to+kio", true, false, false).unwrap();
+        assert_eq!(m.find_lines("tooookio").len(), 1);
+        assert!(Matcher::new("(", true, false, false).is_err());
+    }
+
+    #[test]
+    fn whole_word_test() {
+        let m = Matcher::new("c++", false, false, true).unwrap();
+        assert_eq!(
+            m.find_lines("I like c++ and c++11.")[0].spans,
+            vec![(7, 10)]
+        );
+        let m = Matcher::new("#todo", false, false, true).unwrap();
+        let lines = m.find_lines(
+            "#todo first
+x#todo
+#todos
+then #todo",
+        );
+        let numbers: Vec<usize> = lines.iter().map(|l| l.number).collect();
+        assert_eq!(numbers, vec![1, 4]);
+        let m = Matcher::new("a a", false, false, true).unwrap();
+        assert_eq!(m.find_lines("a a a")[0].spans, vec![(0, 3)]);
+    }
+
+    #[test]
+    fn highlighted_test() {
+        let m = Matcher::new("b", false, false, false).unwrap();
+        let line = &m.find_lines("abc")[0];
+        assert_eq!(line.highlighted(false), "abc");
+        assert!(line.highlighted(true).contains("\u{1b}["));
+    }
+}
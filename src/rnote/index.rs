@@ -0,0 +1,246 @@
+use crate::rnote::{
+    note::Note,
+    notes::{get_base_path, get_note_files},
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Bump when the layout of the index changes so old files get rebuilt.
+const VERSION: u32 = 1;
+
+/// Name of the index file in the root directory of all notes.
+const INDEX_FILE: &str = ".index.json";
+
+/// State of an indexed file, used to detect changes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FileEntry {
+    modified: u64,
+    len: u64,
+    terms: Vec<String>,
+}
+
+/// Inverted index mapping every term to the notes containing it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    version: u32,
+    /// Indexed notes by path relative to the root of all notes.
+    files: BTreeMap<String, FileEntry>,
+    /// Term -> note -> number of occurrences.
+    terms: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+/// Split `text` into lowercase terms.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Get the path to the index file.
+fn get_index_path() -> Result<PathBuf> {
+    Ok(PathBuf::from(get_base_path()?).join(INDEX_FILE))
+}
+
+/// Modification time in nanoseconds and size of a file.
+fn file_state(path: &Path) -> Result<(u64, u64)> {
+    let meta = fs::metadata(path)?;
+    let modified = meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos() as u64;
+    Ok((modified, meta.len()))
+}
+
+impl Index {
+    /// Read the index from `path`, starting from an empty one if it is missing,
+    /// unreadable or outdated.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Index>(&s).ok())
+            .filter(|i| i.version == VERSION)
+            .unwrap_or(Index {
+                version: VERSION,
+                ..Default::default()
+            })
+    }
+
+    /// Write the index to `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut f = fs::File::create(path)?;
+        f.set_permissions(fs::Permissions::from_mode(0o600))?;
+        f.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Number of indexed notes.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Index the `content` of note `name`, replacing any previous entry.
+    fn add(&mut self, name: &str, modified: u64, len: u64, content: &str) {
+        self.remove(name);
+        let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+        for term in tokenize(content) {
+            *counts.entry(term).or_insert(0) += 1;
+        }
+        for (term, count) in &counts {
+            self.terms
+                .entry(term.clone())
+                .or_default()
+                .insert(name.to_owned(), *count);
+        }
+        self.files.insert(
+            name.to_owned(),
+            FileEntry {
+                modified,
+                len,
+                terms: counts.into_keys().collect(),
+            },
+        );
+    }
+
+    /// Drop note `name` from the index.
+    fn remove(&mut self, name: &str) {
+        if let Some(entry) = self.files.remove(name) {
+            for term in entry.terms {
+                if let Some(postings) = self.terms.get_mut(&term) {
+                    postings.remove(name);
+                    if postings.is_empty() {
+                        self.terms.remove(&term);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Bring the index in line with the notes under `base`.
+    /// Return `true` if anything changed.
+    fn sync(&mut self, base: &Path) -> Result<bool> {
+        let mut changed = false;
+        let mut seen: BTreeSet<String> = BTreeSet::new();
+        for path in get_note_files(&base.to_string_lossy())? {
+            let name = match path.strip_prefix(base) {
+                Ok(p) => p.to_string_lossy().into_owned(),
+                Err(_) => continue,
+            };
+            let (modified, len) = file_state(&path)?;
+            let fresh = self
+                .files
+                .get(&name)
+                .map(|e| e.modified == modified && e.len == len)
+                .unwrap_or(false);
+            if !fresh {
+                // Notes that can't be read are left out, `load_notes` reports them.
+                let note = match Note::load(&path, base) {
+                    Ok(note) => note,
+                    Err(_) => continue,
+                };
+                let content = format!(
+                    "{}\n{}\n{}",
+                    note.front.title,
+                    note.front.tags.join(" "),
+                    note.body
+                );
+                self.add(&name, modified, len, &content);
+                changed = true;
+            }
+            seen.insert(name);
+        }
+        let gone: Vec<String> = self
+            .files
+            .keys()
+            .filter(|k| !seen.contains(*k))
+            .cloned()
+            .collect();
+        for name in gone {
+            self.remove(&name);
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /// Find notes containing every term of `query`, best ranked first.
+    /// A query term matches any indexed term starting with it.
+    pub fn search(&self, query: &str) -> Vec<(String, u32)> {
+        let mut scores: Option<BTreeMap<String, u32>> = None;
+        for token in tokenize(query) {
+            let mut found: BTreeMap<String, u32> = BTreeMap::new();
+            let matching = self
+                .terms
+                .range(token.clone()..)
+                .take_while(|(t, _)| t.starts_with(&token));
+            for (_, postings) in matching {
+                for (name, count) in postings {
+                    *found.entry(name.clone()).or_insert(0) += count;
+                }
+            }
+            scores = Some(match scores {
+                None => found,
+                Some(prev) => prev
+                    .into_iter()
+                    .filter_map(|(name, score)| found.get(&name).map(|c| (name, score + c)))
+                    .collect(),
+            });
+        }
+        let mut results: Vec<(String, u32)> = scores.unwrap_or_default().into_iter().collect();
+        results.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+}
+
+/// Load the index and update it with the notes changed since last time.
+pub fn update() -> Result<Index> {
+    let base = PathBuf::from(get_base_path()?);
+    let path = get_index_path()?;
+    let mut index = Index::load(&path);
+    if index.sync(&base)? && base.exists() {
+        index.save(&path)?;
+    }
+    Ok(index)
+}
+
+/// Rebuild the index from scratch and return the number of indexed notes.
+pub fn rebuild() -> Result<usize> {
+    let path = get_index_path()?;
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    Ok(update()?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_test() {
+        assert_eq!(
+            tokenize("Hello, rust_lang world!\n# Title"),
+            vec!["hello", "rust_lang", "world", "title"]
+        );
+    }
+
+    #[test]
+    fn add_remove_search_test() {
+        let mut index = Index::default();
+        index.add("a.md", 0, 0, "rust rust tokio");
+        index.add("b.md", 0, 0, "rust async");
+        let names: Vec<String> = index.search("rust").into_iter().map(|r| r.0).collect();
+        assert_eq!(names, vec!["a.md", "b.md"]);
+        assert_eq!(index.search("rust async"), vec![("b.md".to_owned(), 2)]);
+        assert_eq!(index.search("tok").len(), 1);
+        assert_eq!(index.search("RUST").len(), 2);
+        assert!(index.search("okio").is_empty());
+        index.remove("a.md");
+        assert!(index.search("tokio").is_empty());
+        assert!(!index.terms.contains_key("tokio"));
+    }
+}
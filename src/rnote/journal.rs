@@ -0,0 +1,177 @@
+use crate::rnote::{
+    config,
+    dates::{self, parse_date},
+    note::Note,
+    notes::{self, get_base_path},
+    template,
+};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+
+/// Format of the name of a journal note.
+const ENTRY_FORMAT: &str = "%Y-%m-%d";
+
+/// Parse the date of a journal entry, e.g. `2021-05-03`.
+pub fn parse_day(s: &str) -> Result<NaiveDate> {
+    parse_date(s).ok_or_else(|| anyhow!("Invalid date \"{}\", expected e.g. 2021-05-03.", s))
+}
+
+/// Parse a month, e.g. `2021-05`.
+pub fn parse_month(s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", s.trim()), "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid month \"{}\", expected e.g. 2021-05.", s))
+}
+
+/// Get the path of the journal entry of `date`.
+fn entry_path(date: NaiveDate) -> Result<PathBuf> {
+    let config = config::get();
+    Ok(PathBuf::from(get_base_path()?)
+        .join(&config.journal_category)
+        .join(format!(
+            "{}.{}",
+            date.format(ENTRY_FORMAT),
+            config.extension
+        )))
+}
+
+/// Get the dates having a journal entry.
+fn entry_dates() -> Result<BTreeSet<NaiveDate>> {
+    let dir = PathBuf::from(get_base_path()?).join(&config::get().journal_category);
+    let mut dates = BTreeSet::new();
+    if !dir.exists() {
+        return Ok(dates);
+    }
+    for file in fs::read_dir(dir)? {
+        let path = file?.path();
+        let is_note = path
+            .extension()
+            .map(|e| *e == *config::get().extension)
+            .unwrap_or(false);
+        let date = path
+            .file_stem()
+            .and_then(|s| NaiveDate::parse_from_str(&s.to_string_lossy(), ENTRY_FORMAT).ok());
+        if let (true, Some(date)) = (is_note, date) {
+            dates.insert(date);
+        }
+    }
+    Ok(dates)
+}
+
+/// Open the journal entry of `date`, creating it from the journal template if
+/// there is one.
+pub fn open(date: NaiveDate) -> Result<()> {
+    let path = entry_path(date)?;
+    if path.exists() {
+        let note = Note::load(&path, &PathBuf::from(get_base_path()?))?;
+        return notes::edit_note(&note).map(|_| ());
+    }
+    let config = config::get();
+    let name = date.format(ENTRY_FORMAT).to_string();
+    match template::load(&config.journal_template) {
+        Ok(_) => notes::create_from_template(
+            &name,
+            &config.journal_category,
+            &[],
+            dates::at(date),
+            &config.journal_template,
+            &BTreeMap::new(),
+        ),
+        Err(_) => notes::create_on(&name, &config.journal_category, &[], dates::at(date)),
+    }
+}
+
+/// Show the journal entries of the week of `date` in the viewer.
+pub fn show_week(date: NaiveDate) -> Result<()> {
+    let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+    let base = PathBuf::from(get_base_path()?);
+    let mut entries: Vec<Note> = Vec::new();
+    for day in 0..7 {
+        let path = entry_path(monday + Duration::days(day))?;
+        if path.exists() {
+            entries.push(Note::load(&path, &base)?);
+        }
+    }
+    if entries.is_empty() {
+        return Err(anyhow!(
+            "No journal entries in the week of {}.",
+            monday.format(&config::get().display_date_format)
+        ));
+    }
+    notes::show_notes(&entries)
+}
+
+/// Render the calendar of the month of `month`, marking days in `dates`
+/// with a `*`.
+fn month_grid(month: NaiveDate, dates: &BTreeSet<NaiveDate>) -> String {
+    let first = month.with_day(1).unwrap_or(month);
+    let mut grid = format!("{:^28}", first.format("%B %Y").to_string())
+        .trim_end()
+        .to_owned();
+    grid.push('\n');
+    grid.push_str(" Mo  Tu  We  Th  Fr  Sa  Su\n");
+    let offset = first.weekday().num_days_from_monday() as usize;
+    grid.push_str(&"    ".repeat(offset));
+    let mut day = first;
+    while day.month() == first.month() {
+        let mark = match dates.contains(&day) {
+            true => '*',
+            false => ' ',
+        };
+        grid.push_str(&format!(" {:>2}{}", day.day(), mark));
+        if day.weekday().num_days_from_monday() == 6 {
+            grid = grid.trim_end().to_owned();
+            grid.push('\n');
+        }
+        day += Duration::days(1);
+    }
+    format!("{}\n", grid.trim_end())
+}
+
+/// Print the calendar of the month of `month` with the days having an entry.
+pub fn calendar(month: NaiveDate) -> Result<()> {
+    let dates = entry_dates()?;
+    print!("{}", month_grid(month, &dates));
+    let count = dates
+        .iter()
+        .filter(|d| d.year() == month.year() && d.month() == month.month())
+        .count();
+    println!("\n{} entry(ies) marked with *.", count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_grid_test() {
+        let month = NaiveDate::from_ymd_opt(2021, 2, 1).unwrap();
+        let mut dates = BTreeSet::new();
+        dates.insert(NaiveDate::from_ymd_opt(2021, 2, 3).unwrap());
+        dates.insert(NaiveDate::from_ymd_opt(2021, 3, 3).unwrap());
+        let grid = month_grid(month, &dates);
+        let lines: Vec<&str> = grid.lines().collect();
+        assert_eq!(lines[0].trim(), "February 2021");
+        assert_eq!(lines[2], "  1   2   3*  4   5   6   7");
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[5], " 22  23  24  25  26  27  28");
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            parse_day("2021-05-03").unwrap(),
+            NaiveDate::from_ymd_opt(2021, 5, 3).unwrap()
+        );
+        assert!(parse_day("May 3rd").is_err());
+        assert_eq!(
+            parse_month("2021-05").unwrap(),
+            NaiveDate::from_ymd_opt(2021, 5, 1).unwrap()
+        );
+    }
+}
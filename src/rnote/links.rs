@@ -0,0 +1,292 @@
+use crate::rnote::{
+    config,
+    note::{split_header, Note, TagFilter},
+    notes::{get_all_notes, get_note_interractive},
+};
+use anyhow::{anyhow, Result};
+use std::fs;
+
+/// A `[[target]]` link found in a note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// Name or `category/name` of the linked note.
+    pub target: String,
+    /// Text shown for the link, the target unless given after a `|`.
+    pub text: String,
+    /// Line of the link in its note, counting from 1.
+    pub line: usize,
+    /// Byte range of the whole `[[...]]` in its line.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Find every `[[target]]` or `[[target|text]]` link in `text`, skipping
+/// fenced code blocks.
+pub fn find_links(text: &str) -> Vec<Link> {
+    let mut links: Vec<Link> = Vec::new();
+    let mut in_code = false;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let mut offset = 0;
+        while let Some(start) = line[offset..].find("[[").map(|s| s + offset) {
+            let end = match line[start + 2..].find("]]") {
+                Some(end) => start + 2 + end + 2,
+                None => break,
+            };
+            let inner = &line[start + 2..end - 2];
+            let (target, text) = match inner.split_once('|') {
+                Some((target, text)) => (target.trim(), text.trim()),
+                None => (inner.trim(), inner.trim()),
+            };
+            if !target.is_empty() {
+                links.push(Link {
+                    target: target.to_owned(),
+                    text: text.to_owned(),
+                    line: i + 1,
+                    start,
+                    end,
+                });
+            }
+            offset = end;
+        }
+    }
+    links
+}
+
+/// Find the links of `note`, with line numbers counted in the whole file.
+pub fn note_links(note: &Note) -> Result<Vec<Link>> {
+    let content = fs::read_to_string(&note.path)?;
+    let body = split_header(&content).1;
+    let offset = content[..content.len() - body.len()].matches('\n').count();
+    Ok(find_links(body)
+        .into_iter()
+        .map(|l| Link {
+            line: l.line + offset,
+            ..l
+        })
+        .collect())
+}
+
+/// Check if `target` points to `note`: by `category/name`, else by name or
+/// title, ignoring case.
+pub fn points_to(target: &str, note: &Note) -> bool {
+    let extension = format!(".{}", config::get().extension);
+    let target = target.trim_end_matches(&extension).to_lowercase();
+    let path = match note.category.is_empty() {
+        true => note.name(),
+        false => format!("{}/{}", note.category, note.name()),
+    };
+    match target.contains('/') {
+        true => path.to_lowercase() == target,
+        false => note.name().to_lowercase() == target || note.front.title.to_lowercase() == target,
+    }
+}
+
+/// Get the notes `target` points to.
+pub fn resolve<'a>(target: &str, notes: &'a [Note]) -> Vec<&'a Note> {
+    notes.iter().filter(|n| points_to(target, n)).collect()
+}
+
+/// Point the links to every note of `moves` at where it went, in every note,
+/// once notes were renamed or moved. Links that still point to them are left
+/// as they are. Return the number of notes changed.
+pub fn relink(moves: &[(Note, Note)]) -> Result<usize> {
+    let notes = get_all_notes()?;
+    // Links are resolved among the notes as they were before moving.
+    let before: Vec<Note> = notes
+        .iter()
+        .map(|n| match moves.iter().find(|(_, to)| to.path == n.path) {
+            Some((from, _)) => from.clone(),
+            None => n.clone(),
+        })
+        .collect();
+    let mut changed = 0;
+    for note in notes {
+        let content = fs::read_to_string(&note.path)?;
+        if let Some(content) = retarget(&content, &note_links(&note)?, moves, &before) {
+            fs::write(&note.path, content)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+/// Point the `links` of `content` to a note of `moves` at where it went,
+/// returning the new content if any link changed. Only links pointing to that
+/// note alone among `all` notes before moving are changed.
+fn retarget(content: &str, links: &[Link], moves: &[(Note, Note)], all: &[Note]) -> Option<String> {
+    let starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let mut content = content.to_owned();
+    let mut edited = false;
+    for link in links.iter().rev() {
+        let targets = resolve(&link.target, all);
+        let to = match moves.iter().find(|(from, to)| {
+            matches!(targets.as_slice(), [only] if only.path == from.path)
+                && !points_to(&link.target, to)
+        }) {
+            Some((_, to)) => to,
+            None => continue,
+        };
+        let target = match (link.target.contains('/'), to.category.is_empty()) {
+            (true, false) => format!("{}/{}", to.category, to.name()),
+            _ => to.name(),
+        };
+        let text = match link.text == link.target {
+            true => format!("[[{}]]", target),
+            false => format!("[[{}|{}]]", target, link.text),
+        };
+        let start = starts[link.line - 1];
+        content.replace_range(start + link.start..start + link.end, &text);
+        edited = true;
+    }
+    edited.then_some(content)
+}
+
+/// Pick the note named `name`, prompting if several match.
+fn get_note(name: &str, exact: bool) -> Result<Note> {
+    get_note_interractive(name, &TagFilter::default(), exact)?
+        .ok_or_else(|| anyhow!("No note chosen."))
+}
+
+/// Print the links of `notes`, with the notes they point to. Only print
+/// dangling links if `dangling` is set.
+fn print_links(notes: &[Note], all: &[Note], dangling: bool) -> Result<usize> {
+    let mut count = 0;
+    for note in notes {
+        for link in note_links(note)? {
+            let targets = resolve(&link.target, all);
+            if dangling && !targets.is_empty() {
+                continue;
+            }
+            let to = match targets.is_empty() {
+                true => "(dangling)".to_owned(),
+                false => targets
+                    .iter()
+                    .map(|n| n.relative_path())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            };
+            println!(
+                "{}:{}: [[{}]] -> {}",
+                note.relative_path(),
+                link.line,
+                link.target,
+                to
+            );
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Print the outgoing links of the note `name`, or of every note if no name is
+/// given. Only print dangling links if `dangling` is set.
+pub fn links(name: Option<&str>, exact: bool, dangling: bool) -> Result<()> {
+    let all = get_all_notes()?;
+    let notes = match name {
+        Some(name) => vec![get_note(name, exact)?],
+        None => all.clone(),
+    };
+    let count = print_links(&notes, &all, dangling)?;
+    if count == 0 {
+        match dangling {
+            true => println!("No dangling links."),
+            false => println!("No links."),
+        }
+    }
+    Ok(())
+}
+
+/// Print every link pointing to the note `name`.
+pub fn backlinks(name: &str, exact: bool) -> Result<()> {
+    let note = get_note(name, exact)?;
+    let mut count = 0;
+    for other in get_all_notes()? {
+        for link in note_links(&other)? {
+            if points_to(&link.target, &note) {
+                println!(
+                    "{}:{}: [[{}]]",
+                    other.relative_path(),
+                    link.line,
+                    link.target
+                );
+                count += 1;
+            }
+        }
+    }
+    if count == 0 {
+        println!("No notes link to {}.", note.relative_path());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_links_test() {
+        let text = "See [[todo]] and [[work/Plan | the plan]].\n```\n[[code]]\n```\n[[]] [[last]]";
+        let links = find_links(text);
+        let targets: Vec<&str> = links.iter().map(|l| l.target.as_str()).collect();
+        assert_eq!(targets, vec!["todo", "work/Plan", "last"]);
+        assert_eq!(links[1].text, "the plan");
+        assert_eq!(links[2].line, 5);
+        assert_eq!((links[2].start, links[2].end), (5, 13));
+    }
+
+    fn note(category: &str, name: &str, title: &str) -> Note {
+        Note::sample(category, name, &format!("title: {}", title))
+    }
+
+    #[test]
+    fn resolve_test() {
+        let notes = vec![
+            note("work", "plan", "Q3 plan"),
+            note("home", "plan", "Garden"),
+        ];
+        assert_eq!(resolve("plan", &notes).len(), 2);
+        assert_eq!(resolve("work/Plan", &notes).len(), 1);
+        assert_eq!(resolve("q3 plan", &notes).len(), 1);
+        assert!(resolve("home/todo", &notes).is_empty());
+    }
+
+    #[test]
+    fn retarget_test() {
+        let moves = vec![(
+            note("work", "plan", "Q3 plan"),
+            note("archive", "roadmap", "Q3 plan"),
+        )];
+        let all = vec![moves[0].0.clone(), note("home", "todo", "todo")];
+        let content = "See [[plan]], [[work/plan|it]] and [[Q3 plan]].\n[[todo]]\n";
+        let links = find_links(content);
+        assert_eq!(
+            retarget(content, &links, &moves, &all).unwrap(),
+            "See [[roadmap]], [[archive/roadmap|it]] and [[Q3 plan]].\n[[todo]]\n"
+        );
+        let todo = find_links("[[todo]]");
+        assert_eq!(retarget("[[todo]]", &todo, &moves, &all), None);
+    }
+
+    #[test]
+    fn retarget_ambiguous_test() {
+        let moves = vec![(
+            note("work", "plan", "Q3 plan"),
+            note("archive", "roadmap", "Q3 plan"),
+        )];
+        let all = vec![moves[0].0.clone(), note("home", "plan", "Garden")];
+        let content = "[[plan]] [[work/plan]]\n";
+        assert_eq!(
+            retarget(content, &find_links(content), &moves, &all).unwrap(),
+            "[[plan]] [[archive/roadmap]]\n"
+        );
+    }
+}
@@ -1,4 +1,21 @@
 pub mod app;
+mod browse;
+mod category;
+pub mod config;
+mod confirm;
+mod dates;
+mod fuzzy;
+mod git;
+mod grep;
+mod index;
+mod journal;
+mod links;
+mod note;
+mod notebook;
 mod notes;
+mod output;
 pub mod process;
+mod query;
 pub mod show;
+mod template;
+mod trash;
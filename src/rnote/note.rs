@@ -0,0 +1,422 @@
+use crate::rnote::{
+    config,
+    dates::{self, Timestamp},
+};
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Parsed YAML header of a note.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    pub title: String,
+    pub author: String,
+    pub date: Option<Timestamp>,
+    /// Last time the note was changed with `rnote edit`.
+    pub updated: Option<Timestamp>,
+    /// Number of times the note was changed with `rnote edit`.
+    pub revisions: u64,
+    pub tags: Vec<String>,
+    /// Every other key found in the header.
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// A note loaded from disk.
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub path: PathBuf,
+    /// Directory of the note relative to the root of all notes.
+    pub category: String,
+    pub front: FrontMatter,
+    pub body: String,
+}
+
+/// Order of listed notes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sort {
+    /// Newest first.
+    Date,
+    Title,
+    /// Last edited first.
+    Updated,
+}
+
+impl Sort {
+    /// Compare `a` and `b`, then their titles.
+    pub fn compare(self, a: &Note, b: &Note) -> Ordering {
+        let title = |n: &Note| n.front.title.to_lowercase();
+        let order = match self {
+            Sort::Date => Reverse(a.front.date).cmp(&Reverse(b.front.date)),
+            Sort::Title => Ordering::Equal,
+            Sort::Updated => Reverse(a.updated()).cmp(&Reverse(b.updated())),
+        };
+        order.then_with(|| title(a).cmp(&title(b)))
+    }
+}
+
+impl FromStr for Sort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "date" => Ok(Sort::Date),
+            "title" => Ok(Sort::Title),
+            "updated" => Ok(Sort::Updated),
+            _ => Err(anyhow!("Unknown sort \"{}\".", s)),
+        }
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sort::Date => write!(f, "date"),
+            Sort::Title => write!(f, "title"),
+            Sort::Updated => write!(f, "updated"),
+        }
+    }
+}
+
+/// Filter notes by tags, requiring all of them or any of them.
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    pub tags: Vec<String>,
+    pub any: bool,
+}
+
+impl TagFilter {
+    pub fn new(tags: &[String], any: bool) -> Self {
+        TagFilter {
+            tags: normalize_tags(tags),
+            any,
+        }
+    }
+
+    /// Check if `note` passes the filter. An empty filter matches every note.
+    pub fn matches(&self, note: &Note) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let has = |t: &String| note.front.tags.contains(t);
+        match self.any {
+            true => self.tags.iter().any(has),
+            false => self.tags.iter().all(has),
+        }
+    }
+}
+
+impl FrontMatter {
+    /// Create a header for a new note.
+    pub fn new(title: &str, author: &str, date: Timestamp) -> Self {
+        FrontMatter {
+            title: title.to_owned(),
+            author: author.to_owned(),
+            date: Some(date),
+            ..Default::default()
+        }
+    }
+
+    /// Parse the YAML text found between the `---` delimiters.
+    pub fn parse(yaml: &str) -> Self {
+        let map = match serde_yaml::from_str::<Value>(yaml) {
+            Ok(Value::Mapping(m)) => m,
+            _ => parse_loose(yaml),
+        };
+        let mut front = FrontMatter::default();
+        for (key, value) in map {
+            let key = scalar_to_string(&key);
+            match key.as_str() {
+                "title" => front.title = scalar_to_string(&value),
+                "author" => front.author = scalar_to_string(&value),
+                "date" | "updated" => match dates::parse_timestamp(&scalar_to_string(&value)) {
+                    Some(time) if key == "date" => front.date = Some(time),
+                    Some(time) => front.updated = Some(time),
+                    // Keep what can't be read rather than losing it on write.
+                    None => {
+                        front.extra.insert(key, value);
+                    }
+                },
+                "revisions" => front.revisions = scalar_to_string(&value).parse().unwrap_or(0),
+                "tags" => front.tags = normalize_tags(&parse_tags(&value)),
+                _ => {
+                    front.extra.insert(key, value);
+                }
+            }
+        }
+        front
+    }
+
+    /// Serialize the header, including the `---` delimiters.
+    pub fn to_yaml(&self) -> Result<String> {
+        let mut map = Mapping::new();
+        map.insert("title".into(), self.title.clone().into());
+        map.insert("author".into(), self.author.clone().into());
+        if let Some(date) = self.date {
+            map.insert("date".into(), dates::format_timestamp(&date).into());
+        }
+        if let Some(updated) = self.updated {
+            map.insert("updated".into(), dates::format_timestamp(&updated).into());
+        }
+        if self.revisions > 0 {
+            map.insert("revisions".into(), self.revisions.into());
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().cloned().map(Value::from).collect();
+            map.insert("tags".into(), Value::Sequence(tags));
+        }
+        for (k, v) in &self.extra {
+            map.insert(k.clone().into(), v.clone());
+        }
+        let yaml = serde_yaml::to_string(&map)?;
+        let yaml = yaml.trim_start_matches("---\n").trim_end();
+        Ok(format!("---\n{}\n---\n", yaml))
+    }
+}
+
+impl Note {
+    /// Read and parse the note at `path`, `base` being the root of all notes.
+    pub fn load(path: &Path, base: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let (front, body) = split_header(&content);
+        let mut front = front.map(FrontMatter::parse).unwrap_or_default();
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if front.title.is_empty() {
+            front.title = name;
+        }
+        let category = path
+            .parent()
+            .and_then(|p| p.strip_prefix(base).ok())
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Note {
+            path: path.to_owned(),
+            category,
+            front,
+            body: body.to_owned(),
+        })
+    }
+
+    /// Note `name` in `category` with the YAML `header`, not read from disk.
+    #[cfg(test)]
+    pub fn sample(category: &str, name: &str, header: &str) -> Self {
+        Note {
+            path: Path::new("/notes")
+                .join(category)
+                .join(format!("{}.md", name)),
+            category: category.to_owned(),
+            front: FrontMatter::parse(header),
+            body: String::new(),
+        }
+    }
+
+    /// Name of the note, i.e. the file name without extension.
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Path of the note relative to the root of all notes.
+    pub fn relative_path(&self) -> String {
+        let file = self
+            .path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match self.category.is_empty() {
+            true => file,
+            false => format!("{}/{}", self.category, file),
+        }
+    }
+
+    /// Last time the note was edited, else created.
+    pub fn updated(&self) -> Option<Timestamp> {
+        self.front.updated.or(self.front.date)
+    }
+
+    /// Check if the title, the tags or the body contain `word`.
+    pub fn contains(&self, word: &str) -> bool {
+        self.front.title.contains(word)
+            || self.front.tags.iter().any(|t| t.contains(word))
+            || self.body.contains(word)
+    }
+
+    /// Markdown used to display the note, with its metadata on top.
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {}\n", self.front.title);
+        let mut meta: Vec<String> = Vec::new();
+        if !self.front.author.is_empty() {
+            meta.push(self.front.author.clone());
+        }
+        if let Some(date) = self.front.date {
+            meta.push(date.format(&config::get().display_time_format).to_string());
+        }
+        if !self.category.is_empty() {
+            meta.push(self.category.clone());
+        }
+        if !meta.is_empty() {
+            md.push_str(&format!("*{}*\n", meta.join(" · ")));
+        }
+        if !self.front.tags.is_empty() {
+            md.push_str(&format!("`{}`\n", self.front.tags.join("` `")));
+        }
+        md.push('\n');
+        md.push_str(&self.body);
+        md
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.front.title)?;
+        if let Some(date) = self.front.date {
+            write!(f, "  {}", date.format(&config::get().display_date_format))?;
+        }
+        write!(f, "  ({})", self.relative_path())
+    }
+}
+
+/// Split the content of a note into its YAML header, if any, and its body.
+pub fn split_header(content: &str) -> (Option<&str>, &str) {
+    let rest = match content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    {
+        Some(r) => r,
+        None => return (None, content),
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+/// Fallback for headers that are not valid YAML (e.g. unquoted `:` in a title).
+fn parse_loose(yaml: &str) -> Mapping {
+    let mut map = Mapping::new();
+    for line in yaml.lines() {
+        if let Some((k, v)) = line.split_once(':') {
+            map.insert(k.trim().into(), v.trim().into());
+        }
+    }
+    map
+}
+
+/// Trim tags, strip a leading `#` and drop empty or repeated ones.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().trim_start_matches('#').trim();
+        if !tag.is_empty() && !out.iter().any(|t| t == tag) {
+            out.push(tag.to_owned());
+        }
+    }
+    out
+}
+
+fn parse_tags(value: &Value) -> Vec<String> {
+    match value {
+        Value::Sequence(seq) => seq
+            .iter()
+            .map(scalar_to_string)
+            .filter(|s| !s.is_empty())
+            .collect(),
+        _ => scalar_to_string(value)
+            .trim_matches(|c| c == '[' || c == ']')
+            .split(',')
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty())
+            .collect(),
+    }
+}
+
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Null => String::new(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_start_matches("---\n").trim().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_header_test() {
+        let (h, b) = split_header("---\ntitle: a\n---\nbody\n");
+        assert_eq!(h, Some("title: a\n"));
+        assert_eq!(b, "body\n");
+        assert_eq!(split_header("no header"), (None, "no header"));
+    }
+
+    #[test]
+    fn parse_front_matter_test() {
+        let f = FrontMatter::parse("title: a\nauthor: me\ndate: 03-05-2021\ntags: [x, y]\nfoo: 1");
+        assert_eq!(f.title, "a");
+        assert_eq!(f.author, "me");
+        assert_eq!(
+            f.date.map(|d| d.date_naive()),
+            chrono::NaiveDate::from_ymd_opt(2021, 5, 3)
+        );
+        assert_eq!(f.tags, vec!["x", "y"]);
+        assert!(f.extra.contains_key("foo"));
+    }
+
+    #[test]
+    fn keep_invalid_date_test() {
+        let f = FrontMatter::parse("title: a\ndate: someday\nrevisions: 3");
+        assert_eq!(f.date, None);
+        assert_eq!(f.revisions, 3);
+        assert!(f.to_yaml().unwrap().contains("date: someday"));
+    }
+
+    #[test]
+    fn parse_invalid_yaml_test() {
+        let f = FrontMatter::parse("title: a: b\nauthor: me");
+        assert_eq!(f.title, "a: b");
+        assert_eq!(f.author, "me");
+    }
+
+    #[test]
+    fn tag_filter_test() {
+        let mut note = Note::sample("", "a", "title: a\ntags: [work, '#rust', work]");
+        assert_eq!(note.front.tags, vec!["work", "rust"]);
+        note.front.tags.push("draft".to_owned());
+        let tags = vec!["work".to_owned(), "home".to_owned()];
+        assert!(!TagFilter::new(&tags, false).matches(&note));
+        assert!(TagFilter::new(&tags, true).matches(&note));
+        assert!(TagFilter::new(&[], false).matches(&note));
+    }
+
+    #[test]
+    fn front_matter_roundtrip_test() {
+        let date = dates::parse_timestamp("2021-05-03T23:30:00+02:00").unwrap();
+        let mut f = FrontMatter::new("a: b", "me", date);
+        f.tags = vec!["x".to_owned()];
+        f.updated = dates::parse_timestamp("2021-05-04T08:00:00+02:00");
+        f.revisions = 2;
+        let yaml = f.to_yaml().unwrap();
+        let (h, _) = split_header(&yaml);
+        assert_eq!(FrontMatter::parse(h.unwrap()), f);
+    }
+}
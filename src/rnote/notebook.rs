@@ -0,0 +1,185 @@
+use crate::rnote::config;
+use anyhow::{anyhow, Result};
+use std::{collections::BTreeMap, env, fs, os::unix::fs::PermissionsExt, path::PathBuf};
+use toml::{value::Table, Value};
+
+/// Check that `name` can be used as a notebook name.
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    match valid {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Invalid notebook name \"{}\", use letters, digits, - and _.",
+            name
+        )),
+    }
+}
+
+/// Get the `[notebooks]` table of the config file.
+fn notebooks(file: &mut Table) -> Result<&mut Table> {
+    file.entry("notebooks".to_owned())
+        .or_insert_with(|| Value::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("\"notebooks\" is not a table in the config file."))
+}
+
+/// Add the notebook `name` stored in `dir` to the config `file`.
+fn insert(file: &mut Table, name: &str, dir: &str) -> Result<()> {
+    check_name(name)?;
+    let notebooks = notebooks(file)?;
+    if notebooks.contains_key(name) {
+        return Err(anyhow!("Notebook \"{}\" already exists.", name));
+    }
+    notebooks.insert(name.to_owned(), Value::String(dir.to_owned()));
+    Ok(())
+}
+
+/// Remove the notebook `name` from the config `file`, and from being the
+/// default one, returning its directory.
+fn take(file: &mut Table, name: &str) -> Result<String> {
+    let dir = notebooks(file)?
+        .remove(name)
+        .ok_or_else(|| anyhow!("Unknown notebook \"{}\".", name))?;
+    if file.get("default_notebook").and_then(|v| v.as_str()) == Some(name) {
+        file.remove("default_notebook");
+    }
+    Ok(dir.as_str().unwrap_or_default().to_owned())
+}
+
+/// Register the notebook `name` stored in `path`, creating the directory.
+pub fn add(name: &str, path: &str) -> Result<()> {
+    let mut dir = PathBuf::from(path);
+    if dir.is_relative() && !path.starts_with('~') {
+        dir = env::current_dir()?.join(dir);
+    }
+    let dir = dir.to_string_lossy().into_owned();
+    let mut file = config::read_file()?;
+    insert(&mut file, name, &dir)?;
+    config::write_file(&file)?;
+    let root = config::expand_home(&dir);
+    fs::create_dir_all(&root)?;
+    fs::set_permissions(&root, fs::Permissions::from_mode(0o700))?;
+    println!("Added notebook {} in {}.", name, root);
+    Ok(())
+}
+
+/// Unregister the notebook `name`, leaving its notes on disk.
+pub fn remove(name: &str) -> Result<()> {
+    let mut file = config::read_file()?;
+    let dir = take(&mut file, name)?;
+    config::write_file(&file)?;
+    println!("Removed notebook {}. Its notes are left in {}.", name, dir);
+    Ok(())
+}
+
+/// Render every notebook with its directory, marking the `default` one.
+fn render(notebooks: &BTreeMap<String, String>, default: &str) -> String {
+    let width = notebooks
+        .keys()
+        .map(|n| n.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (name, dir) in notebooks {
+        let mark = match name == default {
+            true => '*',
+            false => ' ',
+        };
+        out.push_str(&format!(
+            "{} {:width$}  {}\n",
+            mark,
+            name,
+            dir,
+            width = width
+        ));
+    }
+    out
+}
+
+/// Print every notebook with its directory, marking the one in use.
+pub fn list() -> Result<()> {
+    let config = config::get();
+    match config.notebooks.is_empty() {
+        true => println!("No notebooks. Add one with `rnote notebook add <name> <path>`."),
+        false => print!("{}", render(&config.notebooks, &config.default_notebook)),
+    }
+    Ok(())
+}
+
+/// Make `name` the default notebook, or print the default one if no name is
+/// given. An empty name goes back to `notes_dir`.
+pub fn default(name: Option<&str>) -> Result<()> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let default = config::load(&[])?.default_notebook;
+            match default.is_empty() {
+                true => println!("No default notebook."),
+                false => println!("{}", default),
+            }
+            return Ok(());
+        }
+    };
+    let mut file = config::read_file()?;
+    match name.is_empty() {
+        true => file.remove("default_notebook"),
+        false => file.insert(
+            "default_notebook".to_owned(),
+            Value::String(name.to_owned()),
+        ),
+    };
+    config::write_file(&file)?;
+    match name.is_empty() {
+        true => println!("No default notebook anymore."),
+        false => println!("Default notebook is now {}.", name),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnote::config::Config;
+
+    #[test]
+    fn add_remove_test() {
+        let mut file = Table::new();
+        insert(&mut file, "work", "/notes/work").unwrap();
+        insert(&mut file, "home", "/notes/home").unwrap();
+        assert!(insert(&mut file, "work", "/elsewhere").is_err());
+        assert!(insert(&mut file, "a/b", "/notes/ab").is_err());
+        file.insert("default_notebook".to_owned(), Value::from("work"));
+        assert_eq!(take(&mut file, "work").unwrap(), "/notes/work");
+        assert!(!file.contains_key("default_notebook"));
+        assert!(take(&mut file, "work").is_err());
+        assert!(notebooks(&mut file).unwrap().contains_key("home"));
+    }
+
+    #[test]
+    fn list_test() {
+        let mut notebooks = BTreeMap::new();
+        notebooks.insert("home".to_owned(), "/notes/home".to_owned());
+        notebooks.insert("work".to_owned(), "/notes/work".to_owned());
+        assert_eq!(
+            render(&notebooks, "work"),
+            "  home  /notes/home\n* work  /notes/work\n"
+        );
+    }
+
+    #[test]
+    fn resolve_test() {
+        let mut config = Config {
+            notes_dir: "/notes/main".to_owned(),
+            ..Default::default()
+        };
+        config
+            .notebooks
+            .insert("work".to_owned(), "/notes/work".to_owned());
+        assert_eq!(config.notes_dir(), "/notes/main");
+        config.default_notebook = "work".to_owned();
+        assert_eq!(config.notes_dir(), "/notes/work");
+    }
+}
@@ -1,71 +1,139 @@
-use crate::rnote::show;
+use crate::rnote::{
+    browse, config,
+    confirm::Confirmation,
+    dates::{self, DateRange, Timestamp},
+    fuzzy, git,
+    grep::Matcher,
+    index, links,
+    note::{normalize_tags, split_header, FrontMatter, Note, Sort, TagFilter},
+    output::{self, Format, NotFound, NoteRecord},
+    query, show, template, trash,
+};
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Utc};
 use dialoguer::{theme::ColorfulTheme, Select};
-use std::{env, fs, io::Write, os::unix::fs::PermissionsExt, path::PathBuf, process::Command};
-use walkdir::WalkDir;
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    env, fs,
+    hash::{Hash, Hasher},
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use walkdir::{DirEntry, WalkDir};
 
 /// Get the path to the root directory of all notes.
 pub fn get_base_path() -> Result<String> {
-    let home = env::var("XDG_DATA_HOME")?;
-    Ok(format!("{}/rnote/", home))
+    let dir = config::get().notes_dir();
+    if dir.is_empty() {
+        return Err(anyhow!("No notes directory configured."));
+    }
+    Ok(format!("{}/", dir.trim_end_matches('/')))
 }
 
 /// Get path to a category/date directory.
 fn get_category_path(category: &str) -> Result<String> {
-    let base = get_base_path()?;
-    let date = Utc::now().format("%Y-%m-%d");
-    match category.is_empty() {
-        true => Ok(format!("{}{}/", base, date)),
-        false => Ok(format!("{}{}/", base, category)),
+    Ok(format!("{}{}/", get_base_path()?, category_name(category)))
+}
+
+/// Get the category a new note goes to: `category`, else the default one,
+/// else today's date.
+pub fn category_name(category: &str) -> String {
+    let config = config::get();
+    match (category.is_empty(), config.default_category.is_empty()) {
+        (false, _) => category.to_owned(),
+        (true, false) => config.default_category.clone(),
+        (true, true) => dates::today()
+            .format(&config.category_date_format)
+            .to_string(),
+    }
+}
+
+/// Check that `category` is a path of directories inside the notes directory,
+/// e.g. `work/projects`, and return it without surrounding slashes.
+pub fn check_category(category: &str) -> Result<String> {
+    let category = category.trim().trim_matches('/');
+    let valid = !category.is_empty()
+        && category
+            .split('/')
+            .all(|s| !s.trim().is_empty() && !s.starts_with('.'));
+    match valid {
+        true => Ok(category.to_owned()),
+        false => Err(anyhow!(
+            "Invalid category \"{}\", use names separated by / not starting with a dot.",
+            category
+        )),
     }
 }
 
-/// Get all note paths.
-pub fn get_all_notes() -> Result<Vec<String>> {
-    let path = get_base_path()?;
-    let mut files: Vec<String> = Vec::new();
-    for (_, file) in WalkDir::new(path)
+/// Check if an entry is hidden, i.e. its name starts with a dot.
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.depth() > 0
+        && entry
+            .file_name()
+            .to_str()
+            .map(|s| s.starts_with('.'))
+            .unwrap_or(false)
+}
+
+/// Get the paths of every note found under `dir`, skipping hidden entries.
+pub fn get_note_files(dir: &str) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for file in WalkDir::new(dir)
         .into_iter()
+        .filter_entry(|e| !is_hidden(e))
         .filter_map(|file| file.ok())
-        .enumerate()
     {
-        if file.metadata()?.is_file() {
-            let p = file.path().to_str().unwrap_or("");
-            if !p.is_empty() {
-                files.push(p.to_owned());
-            }
+        let is_note = file
+            .path()
+            .extension()
+            .map(|e| *e == *config::get().extension)
+            .unwrap_or(false);
+        if file.file_type().is_file() && is_note {
+            files.push(file.into_path());
         }
     }
-    if files.is_empty() {
-        Err(anyhow!("No notes found."))
+    files.sort();
+    Ok(files)
+}
+
+/// Load every note found under `dir`. Files that can't be read, e.g. that
+/// are not UTF-8, are reported and skipped.
+fn load_notes(dir: &str) -> Result<Vec<Note>> {
+    let base = PathBuf::from(get_base_path()?);
+    Ok(get_note_files(dir)?
+        .iter()
+        .filter_map(|p| match Note::load(p, &base) {
+            Ok(note) => Some(note),
+            Err(e) => {
+                eprintln!("Warning: skipping {}: {}", p.display(), e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Get all notes.
+pub fn get_all_notes() -> Result<Vec<Note>> {
+    let notes = load_notes(&get_base_path()?)?;
+    if notes.is_empty() {
+        Err(NotFound("No notes found.".to_owned()).into())
     } else {
-        Ok(files)
+        Ok(notes)
     }
 }
 
 /// Get all notes in category.
-pub fn get_notes_in_category(category: &str) -> Result<Vec<String>> {
+pub fn get_notes_in_category(category: &str) -> Result<Vec<Note>> {
     let base = get_base_path()?;
     let path = format!("{}{}", base, category);
-    let mut files: Vec<String> = Vec::new();
-    if std::path::Path::new(&path).exists() {
-        for (_, file) in WalkDir::new(path)
-            .into_iter()
-            .filter_map(|file| file.ok())
-            .enumerate()
-        {
-            if file.metadata()?.is_file() {
-                let p = file.path().to_str().unwrap_or("");
-                if !p.is_empty() {
-                    files.push(p.to_owned());
-                }
-            }
-        }
-        if files.is_empty() {
-            Err(anyhow!("Category is empty."))
+    if Path::new(&path).exists() {
+        let notes = load_notes(&path)?;
+        if notes.is_empty() {
+            Err(NotFound("Category is empty.".to_owned()).into())
         } else {
-            Ok(files)
+            Ok(notes)
         }
     } else {
         Err(anyhow!("Category no found."))
@@ -74,219 +142,472 @@ pub fn get_notes_in_category(category: &str) -> Result<Vec<String>> {
 
 /// Create directory for a note.
 pub fn create_dir(category: &str) -> Result<()> {
-    let base = get_base_path()?;
-    let date = Utc::now().format("%Y-%m-%d");
-    match category.is_empty() {
-        true => {
-            fs::create_dir_all(format!("{}{}", base, date))?;
-            fs::set_permissions(
-                format!("{}{}", base, date),
-                fs::Permissions::from_mode(0o700),
-            )?;
-        }
-        false => {
-            fs::create_dir_all(format!("{}{}", base, category))?;
-            fs::set_permissions(
-                format!("{}{}", base, category),
-                fs::Permissions::from_mode(0o700),
-            )?;
-        }
+    check_category(&category_name(category))?;
+    let base = PathBuf::from(get_base_path()?);
+    let path = PathBuf::from(get_category_path(category)?);
+    fs::create_dir_all(&path)?;
+    // Nested categories get the same permissions at every level.
+    for dir in path.ancestors().take_while(|d| d.starts_with(&base)) {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
     }
-    fs::set_permissions(base, fs::Permissions::from_mode(0o700))?;
     Ok(())
 }
 
-/// Find a path to desired note.
-pub fn get_note_path(name: &str) -> Result<Vec<String>> {
-    let mut paths: Vec<String> = Vec::new();
-    let base = get_base_path()?;
-    let name = format!("{}.md", name);
-    for entry in WalkDir::new(base) {
-        let entry = entry?;
-        let p: String = match entry.path().to_str() {
-            Some(s) => s.to_owned(),
-            None => "".to_owned(),
-        };
-        let file_name: &str = match entry.file_name().to_str() {
-            Some(s) => s,
-            None => "",
-        };
-        if file_name == name {
-            paths.push(p);
+/// Find all notes with the given name, or with a name or title close to it
+/// unless `exact` is set, best match first.
+pub fn get_notes_by_name(name: &str, exact: bool) -> Result<Vec<Note>> {
+    let notes: Vec<Note> = match exact {
+        true => get_all_notes()?
+            .into_iter()
+            .filter(|n| n.name() == name)
+            .collect(),
+        false => {
+            let mut scored: Vec<(u32, Note)> = get_all_notes()?
+                .into_iter()
+                .filter_map(|n| {
+                    let score =
+                        fuzzy::score(name, &n.name()).max(fuzzy::score(name, &n.front.title));
+                    score.map(|s| (s, n))
+                })
+                .collect();
+            scored.sort_by_key(|s| Reverse(s.0));
+            scored.into_iter().map(|(_, n)| n).collect()
         }
-    }
-    if paths.is_empty() {
-        Err(anyhow!("Note not found."))
+    };
+    if notes.is_empty() {
+        Err(NotFound("Note not found.".to_owned()).into())
     } else {
-        Ok(paths)
+        Ok(notes)
     }
 }
 
-/// Find all notes that contain a given string.
-pub fn get_files_by_word(word: &str) -> Result<Vec<String>> {
-    let path = get_base_path()?;
-    let mut paths: Vec<String> = Vec::new();
-    for (_, file) in WalkDir::new(path)
-        .into_iter()
-        .filter_map(|file| file.ok())
-        .enumerate()
-    {
-        if file.metadata()?.is_file() {
-            match fstream::contains(file.path(), word) {
-                Some(b) => {
-                    if b {
-                        let path = file.path().to_str().unwrap_or("");
-                        if !path.is_empty() {
-                            paths.push(path.to_owned());
-                        }
-                    }
-                }
-                None => continue,
+/// Find all notes that contain a given string, most relevant first.
+pub fn get_notes_by_word(word: &str) -> Result<Vec<Note>> {
+    let notes: Vec<Note> = match index::tokenize(word).is_empty() {
+        true => get_all_notes()?
+            .into_iter()
+            .filter(|n| n.contains(word))
+            .collect(),
+        false => {
+            let base = PathBuf::from(get_base_path()?);
+            let mut notes: Vec<Note> = Vec::new();
+            for (name, _) in index::update()?.search(word) {
+                notes.push(Note::load(&base.join(name), &base)?);
             }
+            notes
         }
+    };
+    if notes.is_empty() {
+        Err(NotFound(format!("No files found with word \"{}\"", word)).into())
+    } else {
+        Ok(notes)
     }
-    if paths.len() > 0 {
-        Ok(paths)
+}
+
+/// Keep only the notes passing `filter`.
+fn filter_by_tags(notes: Vec<Note>, filter: &TagFilter) -> Result<Vec<Note>> {
+    let notes: Vec<Note> = notes.into_iter().filter(|n| filter.matches(n)).collect();
+    if notes.is_empty() {
+        Err(NotFound(format!(
+            "No notes found with tags \"{}\"",
+            filter.tags.join(", ")
+        ))
+        .into())
     } else {
-        Err(anyhow!("No files found with word \"{}\"", word))
+        Ok(notes)
+    }
+}
+
+/// Count notes for every tag.
+pub fn get_tags() -> Result<BTreeMap<String, usize>> {
+    let mut tags: BTreeMap<String, usize> = BTreeMap::new();
+    for note in load_notes(&get_base_path()?)? {
+        for tag in note.front.tags {
+            *tags.entry(tag).or_insert(0) += 1;
+        }
     }
+    Ok(tags)
+}
+
+/// Print every tag with its note count, in `format` if given. Only without a
+/// format is finding no tags an error.
+pub fn list_tags(format: Option<Format>) -> Result<()> {
+    let tags = get_tags()?;
+    if tags.is_empty() && format.is_none() {
+        return Err(anyhow!("No tags found."));
+    }
+    output::print_tags(&tags, format.unwrap_or(Format::Plain))
+}
+
+/// Get the path of the note `name` in `category`.
+fn note_file(name: &str, category: &str) -> Result<String> {
+    Ok(format!(
+        "{}{}.{}",
+        get_category_path(category)?,
+        name,
+        config::get().extension
+    ))
 }
 
 /// Create a new note.
-pub fn create(name: &str, category: &str) -> Result<()> {
-    let editor = env::var("EDITOR")?;
-    let file = format!("{}{}.md", get_category_path(category)?, name);
+pub fn create(name: &str, category: &str, tags: &[String]) -> Result<()> {
+    create_on(name, category, tags, dates::now())
+}
+
+/// Create a new note dated `date`.
+pub fn create_on(name: &str, category: &str, tags: &[String], date: Timestamp) -> Result<()> {
+    let mut front = default_header(name, date);
+    front.tags = normalize_tags(tags);
+    write_new(name, category, &front.to_yaml()?, None)
+}
+
+/// Create a new note dated `date` from `template`, filled with `vars` and
+/// prompting for missing ones.
+pub fn create_from_template(
+    name: &str,
+    category: &str,
+    tags: &[String],
+    date: Timestamp,
+    template: &str,
+    vars: &BTreeMap<String, String>,
+) -> Result<()> {
+    is_duplicate(name, category)?;
+    let mut all = BTreeMap::new();
+    all.insert("title".to_owned(), name.to_owned());
+    all.insert("user".to_owned(), env::var("USER").unwrap_or_default());
+    all.insert(
+        "date".to_owned(),
+        date.format(&config::get().date_format).to_string(),
+    );
+    all.insert("category".to_owned(), category_name(category));
+    all.extend(vars.clone());
+    let content = template::fill(&template::load(template)?, all)?;
+    let content = match split_header(&content) {
+        (Some(header), body) => {
+            // The header date is a timestamp whatever `date_format` filled in.
+            let mut front = FrontMatter::parse(header);
+            front.extra.remove("date");
+            front.date = Some(date);
+            if front.title.is_empty() {
+                front.title = name.to_owned();
+            }
+            front.tags.extend(normalize_tags(tags));
+            front.tags = normalize_tags(&front.tags);
+            format!("{}{}", front.to_yaml()?, body)
+        }
+        (None, body) => {
+            let mut front = default_header(name, date);
+            front.tags = normalize_tags(tags);
+            format!("{}{}", front.to_yaml()?, body)
+        }
+    };
+    let (content, line) = template::take_cursor(&content);
+    write_new(name, category, &content, line)
+}
+
+/// Header of a new note named `name` dated `date`.
+fn default_header(name: &str, date: Timestamp) -> FrontMatter {
+    let username = env::var("USER").unwrap_or_default();
+    FrontMatter::new(name, &username, date)
+}
+
+/// Write a new note with `content`, open it with the cursor on `line` and
+/// record it.
+fn write_new(name: &str, category: &str, content: &str, line: Option<usize>) -> Result<()> {
+    let file = note_file(name, category)?;
     create_dir(category)?;
     is_duplicate(name, category)?;
     let mut f = fs::File::create(&file)?;
-    let username = env::var("USER").unwrap_or("".to_owned());
-    let date = Utc::now().format("%d-%m-%Y");
-    let note_name = format!(
-        r#"---
-title: {}
-author: {}
-date: {}
----"#,
-        name, username, date
-    );
     f.set_permissions(fs::Permissions::from_mode(0o600))?;
-    f.write(format!("{}\n", note_name).as_bytes())?;
-    Command::new(editor).arg(&file).status()?;
-    Ok(())
+    f.write_all(content.as_bytes())?;
+    open_in_editor_at(Path::new(&file), line)?;
+    let note = Note::load(Path::new(&file), Path::new(&get_base_path()?))?;
+    git::commit(&format!("Create {}", note.relative_path()))
 }
 
 /// Check if potentially new note name already exists.
 fn is_duplicate(name: &str, category: &str) -> Result<()> {
-    let file = format!("{}{}.md", get_category_path(category)?, name);
-    let path = format!("{}", get_category_path(category)?);
-    for entry in WalkDir::new(path) {
-        let entry = entry?;
-        let p: &str = match entry.path().to_str() {
-            Some(s) => s,
-            None => "",
-        };
-        if p == file {
-            return Err(anyhow!(
-                "Duplicate in the same category/date. Choose another name."
-            ));
+    let file = note_file(name, category)?;
+    if Path::new(&file).exists() {
+        return Err(anyhow!(
+            "Duplicate in the same category/date. Choose another name."
+        ));
+    }
+    Ok(())
+}
+
+/// Editors known to accept `+<line>` to start on a line.
+const LINE_EDITORS: [&str; 7] = ["vi", "vim", "nvim", "nano", "emacs", "micro", "kak"];
+
+/// Open the file at `path` in the configured editor.
+pub fn open_in_editor(path: &Path) -> Result<()> {
+    open_in_editor_at(path, None)
+}
+
+/// Open the file at `path` in the configured editor, on `line` if the editor
+/// supports it.
+fn open_in_editor_at(path: &Path, line: Option<usize>) -> Result<()> {
+    let editor = config::get().editor();
+    let mut words = editor.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| anyhow!("No editor found. Please set VISUAL or EDITOR."))?;
+    let mut cmd = Command::new(program);
+    cmd.args(words);
+    let name = Path::new(program).file_name().unwrap_or_default();
+    if let Some(line) = line {
+        if LINE_EDITORS.iter().any(|e| *name == **e) {
+            cmd.arg(format!("+{}", line));
         }
     }
+    cmd.arg(path).status()?;
     Ok(())
 }
 
-/// Find a path to desired note and prompt to choose one to open.
-pub fn get_note_path_interractive(name: &str) -> Result<Option<String>> {
-    let mut paths: Vec<String> = get_note_path(name)?;
-    let mut p: Vec<String> = paths.clone();
-    let r = p[0].find("rnote").unwrap_or(0);
-    p = p.into_iter().map(|mut s| s.drain(r..).collect()).collect();
-    if paths.len() == 1 {
-        Ok(Some(paths.remove(0)))
-    } else {
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Optionally choose a note")
-            .default(0)
-            .items(&p)
-            .interact_opt()?;
-        match selection {
-            Some(s) => Ok(Some(paths.remove(s))),
-            None => Ok(None),
+/// Open an existing note in the editor and, if it changed, stamp its header
+/// and record the change. Return whether it changed.
+pub fn edit_note(note: &Note) -> Result<bool> {
+    let changed = edit_file(&note.path, open_in_editor)?;
+    if changed {
+        git::commit(&format!("Edit {}", note.relative_path()))?;
+    }
+    Ok(changed)
+}
+
+/// Edit the file at `path` with `open` and, if it changed, stamp its header.
+/// Return whether it changed.
+fn edit_file(path: &Path, open: impl FnOnce(&Path) -> Result<()>) -> Result<bool> {
+    let before = hash_file(path)?;
+    open(path)?;
+    if hash_file(path)? == before {
+        return Ok(false);
+    }
+    stamp(path, dates::now(), config::get().count_revisions)?;
+    Ok(true)
+}
+
+/// Hash the content of the file at `path`.
+fn hash_file(path: &Path) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    fs::read(path)?.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Set the `updated` field of the header of the note at `path` to `time` and,
+/// if `count`, count one more revision. Notes without header are left as
+/// they are.
+fn stamp(path: &Path, time: Timestamp, count: bool) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    if let (Some(header), body) = split_header(&content) {
+        let mut front = FrontMatter::parse(header);
+        front.updated = Some(time);
+        if count {
+            front.revisions += 1;
         }
+        fs::write(path, format!("{}{}", front.to_yaml()?, body))?;
     }
+    Ok(())
+}
+
+/// Prompt to choose one of `notes`.
+fn select(mut notes: Vec<Note>, prompt: &str) -> Result<Option<Note>> {
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(0)
+        .items(&notes)
+        .interact_opt()?;
+    Ok(selection.map(|s| notes.remove(s)))
+}
+
+/// Take the only note named exactly `name`, else prompt to choose one of `notes`.
+fn pick(mut notes: Vec<Note>, name: &str, prompt: &str) -> Result<Option<Note>> {
+    let exact: Vec<usize> = (0..notes.len())
+        .filter(|i| notes[*i].name() == name)
+        .collect();
+    match exact.len() {
+        1 => Ok(Some(notes.remove(exact[0]))),
+        _ => select(notes, prompt),
+    }
+}
+
+/// Find a desired note and prompt to choose one if there is no single exact match.
+pub fn get_note_interractive(name: &str, filter: &TagFilter, exact: bool) -> Result<Option<Note>> {
+    let notes = filter_by_tags(get_notes_by_name(name, exact)?, filter)?;
+    pick(notes, name, "Optionally choose a note")
 }
 
 /// Delete a note.
-pub fn remove_note(name: &str) -> Result<()> {
-    let mut paths = get_note_path(name)?;
-    if paths.len() == 1 {
-        println!("Deleting...");
-        fs::remove_file(paths.remove(0))?;
-        remove_empty_dirs()?;
-        println!("Successfully deleted.");
-        Ok(())
-    } else {
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Choose a note to delete")
-            .items(&paths)
-            .interact_opt()?;
-        match selection {
-            Some(s) => {
-                fs::remove_file(paths.remove(s))?;
-                remove_empty_dirs()?;
-                println!("Successfully deleted.");
-                Ok(())
-            }
-            None => {
-                println!("Canceling...");
-                Ok(())
-            }
+pub fn remove_note(name: &str, exact: bool, confirmation: &Confirmation) -> Result<()> {
+    let notes = get_notes_by_name(name, exact)?;
+    match pick(notes, name, "Choose a note to delete")? {
+        Some(n) => trash_notes(&[n], confirmation),
+        None => {
+            println!("Canceling...");
+            Ok(())
         }
     }
 }
 
-pub fn remove_category(category: &str) -> Result<()> {
-    let base = get_base_path()?;
-    let path = format!("{}{}", base, category);
-    fs::metadata(&path)?; // check if dir exists, else give error msg.
-    println!("Deleting...");
-    fs::remove_dir_all(path)?;
-    println!("Successfully deleted.");
+/// Move `notes` to the trash once confirmed and print how many were moved.
+fn trash_notes(notes: &[Note], confirmation: &Confirmation) -> Result<()> {
+    let items: Vec<String> = notes.iter().map(|n| n.relative_path()).collect();
+    if !confirmation.ask("moved to trash", &items)? {
+        return Ok(());
+    }
+    trash(notes)?;
+    println!(
+        "Moved {} note(s) to trash. Use `rnote trash restore <name>` to undo.",
+        notes.len()
+    );
     Ok(())
 }
 
-/// Remove all notes created at the given date in format `YYYY-MM-dd`.
-pub fn remove_by_date(date: &str) -> Result<()> {
-    let base = get_base_path()?;
-    let mut ok: bool = false;
-    for (_, file) in WalkDir::new(base)
-        .into_iter()
-        .filter_map(|file| file.ok())
-        .enumerate()
-    {
-        if file.metadata()?.is_file() {
-            let time: DateTime<Utc> = file.metadata()?.created()?.into();
-            if time.format("%Y-%m-%d").to_string() == date {
-                ok = true;
-                // TODO: add verbose flag and prompt to delete each file.
-                fs::remove_file(file.path())?;
-            }
-        }
+/// Move `notes` to the trash and record it.
+pub fn trash(notes: &[Note]) -> Result<()> {
+    let items: Vec<String> = notes.iter().map(|n| n.relative_path()).collect();
+    for note in notes {
+        trash::trash_note(&note.path)?;
     }
-    if !ok {
-        return Err(anyhow!("No files with this date found."));
+    remove_empty_dirs()?;
+    git::commit(&format!("Remove {}", items.join(", ")))
+}
+
+/// Move the file of `note` to `path`, updating its title if it was its old
+/// name, and return the moved note.
+pub fn move_file(note: &Note, path: &Path) -> Result<Note> {
+    if path.exists() {
+        return Err(anyhow!(
+            "Duplicate in the same category/date. Choose another name."
+        ));
+    }
+    fs::rename(&note.path, path)?;
+    let content = fs::read_to_string(path)?;
+    if let (Some(header), body) = split_header(&content) {
+        let mut front = FrontMatter::parse(header);
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if front.title == note.name() && name != note.name() {
+            front.title = name;
+            fs::write(path, format!("{}{}", front.to_yaml()?, body))?;
+        }
     }
+    Note::load(path, Path::new(&get_base_path()?))
+}
+
+/// Move the file of `note` to `path` like `move_file`, update the links
+/// pointing to it and record it as `action`. Return the moved note and the
+/// number of notes whose links were updated.
+fn relocate(note: &Note, path: &Path, action: &str) -> Result<(Note, usize)> {
+    let moved = move_file(note, path)?;
+    let relinked = links::relink(&[(note.clone(), moved.clone())])?;
     remove_empty_dirs()?;
+    git::commit(&format!(
+        "{} {} to {}",
+        action,
+        note.relative_path(),
+        moved.relative_path()
+    ))?;
+    Ok((moved, relinked))
+}
+
+/// Move `note` to `category`.
+pub fn move_note(note: &Note, category: &str) -> Result<(Note, usize)> {
+    let category = &check_category(category)?;
+    if *category == note.category {
+        return Err(anyhow!(
+            "{} is already in {}.",
+            note.relative_path(),
+            category
+        ));
+    }
+    let name = note.name();
+    is_duplicate(&name, category)?;
+    create_dir(category)?;
+    relocate(note, Path::new(&note_file(&name, category)?), "Move")
+}
 
+/// Rename `note` to `name`, in the same category.
+pub fn rename_note(note: &Note, name: &str) -> Result<(Note, usize)> {
+    let name = name.trim();
+    if name.is_empty() || name.contains('/') {
+        return Err(anyhow!("Invalid note name \"{}\".", name));
+    }
+    if name == note.name() {
+        return Err(anyhow!(
+            "{} is already named {}.",
+            note.relative_path(),
+            name
+        ));
+    }
+    // Notes at the root have no category to look in, `move_file` checks them.
+    if !note.category.is_empty() {
+        is_duplicate(name, &note.category)?;
+    }
+    let file = format!("{}.{}", name, config::get().extension);
+    relocate(note, &note.path.with_file_name(file), "Rename")
+}
+
+/// Print where `note` went after being renamed or moved.
+pub fn print_moved(note: &Note, moved: &Note, relinked: usize) {
+    println!("{} is now {}.", note.relative_path(), moved.relative_path());
+    if relinked > 0 {
+        println!("Updated links in {} note(s).", relinked);
+    }
+}
+
+/// Delete a category with all its notes and other files, moving its
+/// directory to the trash as a whole.
+pub fn remove_category(category: &str, confirmation: &Confirmation) -> Result<()> {
+    let category = check_category(category)?;
+    let base = PathBuf::from(get_base_path()?);
+    let dir = base.join(&category);
+    if !dir.is_dir() {
+        return Err(anyhow!("Category no found."));
+    }
+    let files: Vec<String> = WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(&base)
+                .ok()
+                .map(|p| p.to_string_lossy().into_owned())
+        })
+        .collect();
+    let action = format!("moved to trash with the category {}", category);
+    if !confirmation.ask_files(&action, &files)? {
+        return Ok(());
+    }
+    trash::trash_note(&dir)?;
+    remove_empty_dirs()?;
+    git::commit(&format!("Remove category {}", category))?;
+    println!(
+        "Moved category {} to trash. Use `rnote trash restore {}` to undo.",
+        category, category
+    );
     Ok(())
 }
 
-/// Remove empty directories.
-fn remove_empty_dirs() -> Result<()> {
+/// Delete all notes.
+pub fn remove_all(confirmation: &Confirmation) -> Result<()> {
+    trash_notes(&get_all_notes()?, confirmation)
+}
+
+/// Remove all notes dated in `range` by their header.
+pub fn remove_by_date(range: &DateRange, confirmation: &Confirmation) -> Result<()> {
+    trash_notes(&range.filter(get_all_notes()?)?, confirmation)
+}
+
+/// Remove empty directories, leaving hidden ones alone.
+pub fn remove_empty_dirs() -> Result<()> {
     let base = get_base_path()?;
-    for (_, file) in WalkDir::new(base)
+    for file in WalkDir::new(base)
         .into_iter()
+        .filter_entry(|e| !is_hidden(e))
         .filter_map(|file| file.ok())
-        .enumerate()
     {
         let is_empty = PathBuf::from(file.path())
             .read_dir()
@@ -301,13 +622,13 @@ fn remove_empty_dirs() -> Result<()> {
 }
 
 /// Modify a note.
-pub fn modify(name: &str) -> Result<()> {
-    let editor = env::var("EDITOR")?;
-    let file = get_note_path_interractive(name)?;
-    match file {
-        Some(f) => {
-            Command::new(editor).arg(f).status()?;
-            println!("Edited successfully!");
+pub fn modify(name: &str, exact: bool) -> Result<()> {
+    match get_note_interractive(name, &TagFilter::default(), exact)? {
+        Some(n) => {
+            match edit_note(&n)? {
+                true => println!("Edited successfully!"),
+                false => println!("No changes."),
+            }
             Ok(())
         }
         None => {
@@ -317,128 +638,149 @@ pub fn modify(name: &str) -> Result<()> {
     }
 }
 
-/// Prompt user to open one of found notes by word.
-pub fn search_by_word(word: &str) -> Result<()> {
-    let mut paths: Vec<String> = get_files_by_word(word)?;
-    let mut p: Vec<String> = paths.clone();
-    let r = p[0].find("rnote").unwrap_or(0);
-    p = p.into_iter().map(|mut s| s.drain(r..).collect()).collect();
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Optionally choose a note")
-        .default(0)
-        .items(&p)
-        .interact_opt()?;
-    if let Some(selection) = selection {
-        let editor = std::env::var("EDITOR")?;
-        std::process::Command::new(editor)
-            .arg(paths.remove(selection))
-            .status()?;
+/// Print `notes` in `format` with their lines matching `matcher`, or prompt to
+/// open one of them if no format is given.
+fn output(notes: Vec<Note>, matcher: Option<&Matcher>, format: Option<Format>) -> Result<()> {
+    match format {
+        Some(format) => {
+            let mut records: Vec<NoteRecord> = Vec::new();
+            for note in &notes {
+                let lines = match matcher {
+                    Some(m) => m.find_lines(&fs::read_to_string(&note.path)?),
+                    None => Vec::new(),
+                };
+                records.push(NoteRecord::new(note, lines));
+            }
+            output::print_notes(&records, format)
+        }
+        None => {
+            if let Some(n) = select(notes, "Optionally choose a note")? {
+                edit_note(&n)?;
+            }
+            Ok(())
+        }
     }
+}
 
-    Ok(())
+/// Find notes by name and open one, or print them in `format`.
+pub fn search_by_name(
+    name: &str,
+    filter: &TagFilter,
+    exact: bool,
+    format: Option<Format>,
+) -> Result<()> {
+    if format.is_some() {
+        let notes = filter_by_tags(get_notes_by_name(name, exact)?, filter)?;
+        return output(notes, None, format);
+    }
+    match get_note_interractive(name, filter, exact)? {
+        Some(n) => edit_note(&n).map(|_| ()),
+        None => Err(anyhow!("Nothing found.")),
+    }
 }
 
-/// Show all notes.
-pub fn show_all() -> Result<()> {
-    let base: String = get_base_path()?;
-    let mut files: Vec<String> = Vec::new();
-    for (_, file) in WalkDir::new(base)
-        .into_iter()
-        .filter_map(|file| file.ok())
-        .enumerate()
-    {
-        if file.metadata()?.is_file() {
-            files.push(fs::read_to_string(file.path())?);
+/// Prompt user to open one of found notes by word.
+pub fn search_by_word(word: &str, filter: &TagFilter, format: Option<Format>) -> Result<()> {
+    let notes = filter_by_tags(get_notes_by_word(word)?, filter)?;
+    let matcher = Matcher::new(word, false, true, false)?;
+    output(notes, Some(&matcher), format)
+}
+
+/// Print every line of every note matching `matcher`, grep-style by default.
+pub fn grep(matcher: &Matcher, filter: &TagFilter, format: Option<Format>) -> Result<()> {
+    let mut notes: Vec<Note> = Vec::new();
+    for note in filter_by_tags(get_all_notes()?, filter)? {
+        if !matcher
+            .find_lines(&fs::read_to_string(&note.path)?)
+            .is_empty()
+        {
+            notes.push(note);
         }
     }
-    if files.is_empty() {
-        return Err(anyhow!("No notes found."));
+    if notes.is_empty() {
+        return Err(NotFound("No matching lines found.".to_owned()).into());
     }
-    let skin = show::make_skin();
-    let md = &files.join("---\n");
-    show::run_app(skin, md)?;
-    Ok(())
+    output(notes, Some(matcher), Some(format.unwrap_or(Format::Plain)))
+}
+
+/// Prompt user to open one of the notes matching a query.
+pub fn search_by_query(input: &str, filter: &TagFilter, format: Option<Format>) -> Result<()> {
+    let query = query::parse(input)?;
+    let notes: Vec<Note> = get_all_notes()?
+        .into_iter()
+        .filter(|n| query.matches(n))
+        .collect();
+    if notes.is_empty() {
+        return Err(NotFound("No notes match the query.".to_owned()).into());
+    }
+    let terms: Vec<String> = query.terms().iter().map(|t| regex::escape(t)).collect();
+    let matcher = match terms.is_empty() {
+        true => None,
+        false => Some(Matcher::new(&terms.join("|"), true, true, false)?),
+    };
+    output(filter_by_tags(notes, filter)?, matcher.as_ref(), format)
+}
+
+/// Display `notes` in the TextView.
+pub fn show_notes(notes: &[Note]) -> Result<()> {
+    show::run_app(show::make_skin()?, show::Page::from_notes(notes))
+}
+
+/// Browse all notes dated in `range`.
+pub fn show_all(filter: &TagFilter, range: &DateRange) -> Result<()> {
+    browse::run(
+        show::make_skin()?,
+        range.filter(filter_by_tags(get_all_notes()?, filter)?)?,
+    )
 }
 
 /// Show one note.
-pub fn show(name: &str) -> Result<()> {
-    let path = get_note_path_interractive(name)?;
-    match path {
-        Some(s) => {
-            let skin = show::make_skin();
-            let content = fs::read_to_string(s)?;
-            show::run_app(skin, &content)?;
-            Ok(())
-        }
+pub fn show(name: &str, filter: &TagFilter, exact: bool) -> Result<()> {
+    match get_note_interractive(name, filter, exact)? {
+        Some(n) => show_notes(&[n]),
         None => Err(anyhow!("Abort.")),
     }
 }
 
-/// Show all notes in the given category.
-pub fn show_category(category: &str) -> Result<()> {
-    let base = get_base_path()?;
-    let path = format!("{}{}", base, category);
-    let mut files: Vec<String> = Vec::new();
-    if std::path::Path::new(&path).exists() {
-        for (_, file) in WalkDir::new(path)
-            .into_iter()
-            .filter_map(|file| file.ok())
-            .enumerate()
-        {
-            if file.metadata()?.is_file() {
-                files.push(fs::read_to_string(file.path())?);
-            }
-        }
-        if files.is_empty() {
-            return Err(anyhow!("No notes found."));
-        }
-        let skin = show::make_skin();
-        let md = &files.join("---\n");
-        show::run_app(skin, md)?;
-        Ok(())
-    } else {
-        Err(anyhow!("Category does not exist."))
-    }
+/// Browse all notes in the given category dated in `range`.
+pub fn show_category(category: &str, filter: &TagFilter, range: &DateRange) -> Result<()> {
+    browse::run(
+        show::make_skin()?,
+        range.filter(filter_by_tags(get_notes_in_category(category)?, filter)?)?,
+    )
 }
 
-/// List all notes and prompt to open one.
-pub fn list_all_notes() -> Result<()> {
-    let mut files: Vec<String> = get_all_notes()?;
-    let mut p: Vec<String> = files.clone();
-    let r = p[0].find("rnote").unwrap_or(0);
-    p = p.into_iter().map(|mut s| s.drain(r..).collect()).collect();
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Optionally choose a note")
-        .default(0)
-        .items(&p)
-        .interact_opt()?;
-    if let Some(selection) = selection {
-        let editor = std::env::var("EDITOR")?;
-        std::process::Command::new(editor)
-            .arg(files.remove(selection))
-            .status()?;
-    }
-    Ok(())
+/// List all notes dated in `range` and prompt to open one, or print them in
+/// `format`.
+pub fn list_all_notes(
+    filter: &TagFilter,
+    range: &DateRange,
+    sort: Option<Sort>,
+    format: Option<Format>,
+) -> Result<()> {
+    let notes = range.filter(filter_by_tags(get_all_notes()?, filter)?)?;
+    output(sorted(notes, sort), None, format)
 }
 
-/// List all notes in the given category and optionally open one.
-pub fn list_category(category: &str) -> Result<()> {
-    let mut files: Vec<String> = get_notes_in_category(category)?;
-    let mut p: Vec<String> = files.clone();
-    let r = p[0].find("rnote").unwrap_or(0);
-    p = p.into_iter().map(|mut s| s.drain(r..).collect()).collect();
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Optionally choose a note")
-        .default(0)
-        .items(&p)
-        .interact_opt()?;
-    if let Some(selection) = selection {
-        let editor = std::env::var("EDITOR")?;
-        std::process::Command::new(editor)
-            .arg(files.remove(selection))
-            .status()?;
+/// List all notes in the given category dated in `range` and optionally open
+/// one, or print them in `format`.
+pub fn list_category(
+    category: &str,
+    filter: &TagFilter,
+    range: &DateRange,
+    sort: Option<Sort>,
+    format: Option<Format>,
+) -> Result<()> {
+    let notes = range.filter(filter_by_tags(get_notes_in_category(category)?, filter)?)?;
+    output(sorted(notes, sort), None, format)
+}
+
+/// Sort `notes` by `sort`, if given.
+fn sorted(mut notes: Vec<Note>, sort: Option<Sort>) -> Vec<Note> {
+    if let Some(sort) = sort {
+        notes.sort_by(|a, b| sort.compare(a, b));
     }
-    Ok(())
+    notes
 }
 
 // Make sure to remove rnote directory before tests.
@@ -454,15 +796,49 @@ mod tests {
     #[test]
     fn find_by_word_test() {
         env::set_var("EDITOR", "echo");
-        assert!(create("test_find_by_word", "test_word").is_ok());
-        assert!(get_files_by_word("test_find").is_ok());
+        assert!(create("test_find_by_word", "test_word", &[]).is_ok());
+        assert!(get_notes_by_word("test_find").is_ok());
     }
 
     #[test]
     fn get_note_path_test() {
         env::set_var("EDITOR", "echo");
-        assert!(create("test", "test_path").is_ok());
-        assert!(get_note_path("test").is_ok());
+        assert!(create("test", "test_path", &[]).is_ok());
+        assert!(get_notes_by_name("test", true).is_ok());
+    }
+
+    #[test]
+    fn load_front_matter_test() {
+        env::set_var("EDITOR", "echo");
+        assert!(create("test_front_matter", "test_front", &[]).is_ok());
+        let notes = get_notes_by_name("test_front_matter", true).unwrap();
+        assert_eq!(notes[0].front.title, "test_front_matter");
+        assert_eq!(notes[0].category, "test_front");
+        assert!(notes[0].front.date.is_some());
+    }
+
+    #[test]
+    fn tags_test() {
+        env::set_var("EDITOR", "echo");
+        let tags = vec!["test_tag".to_owned(), "#test_other".to_owned()];
+        assert!(create("test_tags", "test_tags", &tags).is_ok());
+        let filter = TagFilter::new(&["test_other".to_owned()], false);
+        assert!(filter_by_tags(get_all_notes().unwrap(), &filter).is_ok());
+        assert_eq!(get_tags().unwrap().get("test_tag"), Some(&1));
+    }
+
+    #[test]
+    fn check_category_test() {
+        assert_eq!(
+            check_category(" /work/projects/ ").unwrap(),
+            "work/projects"
+        );
+        assert!(check_category("..").is_err());
+        assert!(check_category("work/../home").is_err());
+        assert!(check_category(".hidden").is_err());
+        assert!(check_category("work//projects").is_err());
+        assert!(check_category("work/ /projects").is_err());
+        assert!(check_category("").is_err());
     }
 
     #[test]
@@ -474,8 +850,39 @@ mod tests {
     #[test]
     fn create_remove_test() {
         env::set_var("EDITOR", "echo");
-        assert!(create("test1", "test1").is_ok());
-        assert!(remove_note("test1").is_ok());
+        assert!(create("test1", "test1", &[]).is_ok());
+        assert!(remove_note(
+            "test1",
+            true,
+            &Confirmation {
+                yes: true,
+                dry_run: false
+            }
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn edit_file_test() {
+        let path = env::temp_dir().join(format!("rnote-edit-test-{}.md", std::process::id()));
+        fs::write(&path, "---\ntitle: a\n---\nbody\n").unwrap();
+        let run = |script: &str, path: &Path| -> Result<()> {
+            Command::new("sh").args(["-c", script]).arg(path).status()?;
+            Ok(())
+        };
+        assert!(!edit_file(&path, |p| run("true", p)).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "---\ntitle: a\n---\nbody\n"
+        );
+        assert!(edit_file(&path, |p| run("echo x >> \"$0\"", p)).unwrap());
+        let front = Note::load(&path, &env::temp_dir()).unwrap().front;
+        assert!(front.updated.is_some());
+        stamp(&path, dates::now(), true).unwrap();
+        let note = Note::load(&path, &env::temp_dir()).unwrap();
+        assert_eq!(note.front.revisions, front.revisions + 1);
+        assert!(note.body.ends_with("body\nx\n"));
+        fs::remove_file(&path).unwrap();
     }
 
     #[test]
@@ -485,15 +892,23 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn remove_by_date_test() {
-        assert!(remove_by_date("1999-10-10").is_ok());
+        assert!(create_dir("test_dated").is_ok());
+        let path = note_file("test_removed_by_date", "test_dated").unwrap();
+        fs::write(&path, "---\ntitle: test\ndate: 1999-10-10\n---\n").unwrap();
+        let range = DateRange::parse(Some("1999-10-10"), None, None).unwrap();
+        let yes = Confirmation {
+            yes: true,
+            dry_run: false,
+        };
+        assert!(remove_by_date(&range, &yes).is_ok());
+        assert!(!Path::new(&path).exists());
     }
 
     #[test]
     fn get_notes_in_category_test() {
         env::set_var("EDITOR", "echo");
-        assert!(create("test_category", "test_c").is_ok());
+        assert!(create("test_category", "test_c", &[]).is_ok());
         assert!(get_notes_in_category("test_c").is_ok());
     }
 }
@@ -0,0 +1,272 @@
+use crate::rnote::{
+    dates::format_timestamp,
+    grep::{self, LineMatch},
+    note::Note,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use crossterm::tty::IsTty;
+use serde::Serialize;
+use std::{collections::BTreeMap, error::Error, fmt, fs, io::stdout, str::FromStr};
+
+/// Non-interactive output formats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Plain,
+    Json,
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(anyhow!("Unknown format \"{}\".", s)),
+        }
+    }
+}
+
+/// Error of a search or listing which found nothing, as opposed to one which
+/// failed. Printed as an empty list when a format is given.
+#[derive(Debug)]
+pub struct NotFound(pub String);
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for NotFound {}
+
+/// A matching line of a note.
+#[derive(Debug, Serialize)]
+pub struct MatchRecord {
+    pub line: usize,
+    pub text: String,
+}
+
+/// A note as printed by `list` and `search`.
+#[derive(Debug, Serialize)]
+pub struct NoteRecord {
+    pub path: String,
+    pub name: String,
+    pub title: String,
+    pub category: String,
+    pub author: String,
+    pub date: Option<String>,
+    pub updated: Option<String>,
+    pub modified: Option<String>,
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<MatchRecord>,
+    #[serde(skip)]
+    summary: String,
+    #[serde(skip)]
+    relative_path: String,
+    #[serde(skip)]
+    line_matches: Vec<LineMatch>,
+}
+
+/// A tag as printed by `tags`.
+#[derive(Debug, Serialize)]
+struct TagRecord<'a> {
+    tag: &'a str,
+    count: usize,
+}
+
+impl NoteRecord {
+    pub fn new(note: &Note, line_matches: Vec<LineMatch>) -> Self {
+        let modified = fs::metadata(&note.path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| DateTime::<Local>::from(t).to_rfc3339());
+        NoteRecord {
+            path: note.path.to_string_lossy().into_owned(),
+            name: note.name(),
+            title: note.front.title.clone(),
+            category: note.category.clone(),
+            author: note.front.author.clone(),
+            date: note.front.date.as_ref().map(format_timestamp),
+            updated: note.front.updated.as_ref().map(format_timestamp),
+            modified,
+            tags: note.front.tags.clone(),
+            matches: line_matches
+                .iter()
+                .map(|m| MatchRecord {
+                    line: m.number,
+                    text: m.line.clone(),
+                })
+                .collect(),
+            summary: note.to_string(),
+            relative_path: note.relative_path(),
+            line_matches,
+        }
+    }
+}
+
+/// Replace characters that would break a TSV row.
+fn tsv_field(s: &str) -> String {
+    s.replace(['\t', '\n'], " ").replace('\r', "")
+}
+
+/// Print an empty list in `format` instead of failing when `result` found
+/// nothing, so scripts get e.g. `[]` and a success.
+pub fn or_empty(result: Result<()>, format: Option<Format>) -> Result<()> {
+    match (result, format) {
+        (Err(e), Some(format)) if e.is::<NotFound>() => print_notes(&[], format),
+        (result, _) => result,
+    }
+}
+
+/// Print notes to stdout in `format`.
+pub fn print_notes(records: &[NoteRecord], format: Format) -> Result<()> {
+    print!("{}", render_notes(records, format, stdout().is_tty())?);
+    Ok(())
+}
+
+/// Render notes in `format`, one line per note or matching line.
+fn render_notes(records: &[NoteRecord], format: Format, color: bool) -> Result<String> {
+    let mut out = String::new();
+    match format {
+        Format::Json => out.push_str(&format!("{}\n", serde_json::to_string_pretty(records)?)),
+        Format::Tsv => {
+            for r in records {
+                let row = format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    tsv_field(&r.path),
+                    tsv_field(&r.title),
+                    tsv_field(&r.category),
+                    r.date.clone().unwrap_or_default(),
+                    r.updated.clone().unwrap_or_default(),
+                    tsv_field(&r.tags.join(","))
+                );
+                match r.matches.is_empty() {
+                    true => out.push_str(&format!("{}\n", row)),
+                    false => {
+                        for m in &r.matches {
+                            let text = tsv_field(&m.text);
+                            out.push_str(&format!("{}\t{}\t{}\n", row, m.line, text));
+                        }
+                    }
+                }
+            }
+        }
+        Format::Plain => {
+            for r in records {
+                match r.line_matches.is_empty() {
+                    true => out.push_str(&format!("{}\n", r.summary)),
+                    false => {
+                        for m in &r.line_matches {
+                            let line = grep::format_match(&r.relative_path, m, color);
+                            out.push_str(&format!("{}\n", line));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Print tags with their note count to stdout in `format`.
+pub fn print_tags(tags: &BTreeMap<String, usize>, format: Format) -> Result<()> {
+    match format {
+        Format::Json => {
+            let records: Vec<TagRecord> = tags
+                .iter()
+                .map(|(tag, count)| TagRecord { tag, count: *count })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&records)?);
+        }
+        Format::Tsv => {
+            for (tag, count) in tags {
+                println!("{}\t{}", tsv_field(tag), count);
+            }
+        }
+        Format::Plain => {
+            let width = tags.keys().map(|t| t.chars().count()).max().unwrap_or(0);
+            for (tag, count) in tags {
+                println!("{:width$}  {}", tag, count, width = width);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnote::grep::Matcher;
+
+    fn record(matcher: Option<&str>) -> NoteRecord {
+        let note = Note::sample(
+            "work",
+            "plan",
+            "title: The\tplan\ndate: 2021-05-03T10:00:00+02:00\ntags: [a, b]",
+        );
+        let lines = match matcher {
+            Some(m) => Matcher::new(m, false, false, false)
+                .unwrap()
+                .find_lines("first\nsecond line\nlast"),
+            None => Vec::new(),
+        };
+        NoteRecord::new(&note, lines)
+    }
+
+    #[test]
+    fn json_test() {
+        let json = render_notes(&[record(None)], Format::Json, false).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["name"], "plan");
+        assert_eq!(value[0]["title"], "The\tplan");
+        assert_eq!(value[0]["date"], "2021-05-03T10:00:00+02:00");
+        assert_eq!(value[0]["updated"], serde_json::Value::Null);
+        assert_eq!(value[0]["tags"], serde_json::json!(["a", "b"]));
+        assert!(value[0].get("matches").is_none());
+
+        let json = render_notes(&[record(Some("second"))], Format::Json, false).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["matches"][0]["line"], 2);
+        assert_eq!(value[0]["matches"][0]["text"], "second line");
+
+        assert_eq!(render_notes(&[], Format::Json, false).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn tsv_test() {
+        assert_eq!(
+            render_notes(&[record(None)], Format::Tsv, false).unwrap(),
+            "/notes/work/plan.md\tThe plan\twork\t2021-05-03T10:00:00+02:00\t\ta,b\n"
+        );
+        assert_eq!(
+            render_notes(&[record(Some("line"))], Format::Tsv, false).unwrap(),
+            "/notes/work/plan.md\tThe plan\twork\t2021-05-03T10:00:00+02:00\t\ta,b\t2\tsecond line\n"
+        );
+        assert_eq!(render_notes(&[], Format::Tsv, false).unwrap(), "");
+    }
+
+    #[test]
+    fn plain_test() {
+        assert_eq!(
+            render_notes(&[record(Some("line"))], Format::Plain, false).unwrap(),
+            "work/plan.md:2:second line\n"
+        );
+        let summary = render_notes(&[record(None)], Format::Plain, false).unwrap();
+        assert!(summary.starts_with("The\tplan  "));
+        assert!(summary.ends_with("  (work/plan.md)\n"));
+        assert_eq!(render_notes(&[], Format::Plain, false).unwrap(), "");
+    }
+
+    #[test]
+    fn or_empty_test() {
+        let not_found = || Err(NotFound("No notes found.".to_owned()).into());
+        assert!(or_empty(not_found(), Some(Format::Json)).is_ok());
+        assert!(or_empty(not_found(), None).is_err());
+        assert!(or_empty(Err(anyhow!("Invalid query.")), Some(Format::Json)).is_err());
+    }
+}
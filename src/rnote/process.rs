@@ -1,7 +1,92 @@
-use crate::rnote::notes;
+use crate::rnote::{
+    category, config,
+    confirm::Confirmation,
+    dates::{self, DateRange},
+    git,
+    grep::Matcher,
+    index, journal, links,
+    note::{Note, Sort, TagFilter},
+    notebook, notes,
+    output::{self, Format},
+    template, trash,
+};
 use anyhow::{anyhow, Result};
+use chrono::Duration;
 use clap::ArgMatches;
 use dialoguer::{theme::ColorfulTheme, Input};
+use std::collections::BTreeMap;
+
+/// Get the tags given with `--tag`.
+fn get_tags(matches: &ArgMatches) -> Vec<String> {
+    matches
+        .values_of("tag")
+        .map(|v| v.map(|s| s.to_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// Build the tag filter from `--tag` and `--any`.
+fn tag_filter(matches: &ArgMatches) -> TagFilter {
+    TagFilter::new(&get_tags(matches), matches.is_present("any"))
+}
+
+/// Get the range of days from `--date`, `--since` and `--until`.
+fn date_range(matches: &ArgMatches) -> Result<DateRange> {
+    DateRange::parse(
+        matches.value_of("date"),
+        matches.value_of("since"),
+        matches.value_of("until"),
+    )
+}
+
+/// Get the output format from `--format` or `--json`, if any.
+fn get_format(matches: &ArgMatches) -> Result<Option<Format>> {
+    if matches.is_present("json") {
+        return Ok(Some(Format::Json));
+    }
+    match matches.value_of("format") {
+        Some(f) => Ok(Some(f.parse()?)),
+        None => Ok(config::get().output_format()),
+    }
+}
+
+/// Get the order of listed notes from `--sort`, if any.
+fn get_sort(matches: &ArgMatches) -> Result<Option<Sort>> {
+    matches.value_of("sort").map(str::parse).transpose()
+}
+
+/// Load the configuration with the overrides given on the command line,
+/// leniently for `config` so that a broken configuration can be fixed.
+pub fn configure(matches: &ArgMatches) -> Result<()> {
+    let sub = matches.subcommand().1;
+    let mut overrides: Vec<(String, String)> = Vec::new();
+    let flags = [
+        ("notes-dir", "notes_dir"),
+        ("notebook", "default_notebook"),
+        ("editor", "editor"),
+    ];
+    for (arg, key) in flags {
+        let value = sub
+            .and_then(|m| m.value_of(arg))
+            .or_else(|| matches.value_of(arg));
+        if let Some(value) = value {
+            // An explicit directory wins over the default notebook.
+            if arg == "notes-dir" {
+                overrides.push(("default_notebook".to_owned(), String::new()));
+            }
+            overrides.push((key.to_owned(), value.to_owned()));
+        }
+    }
+    let lenient = matches.subcommand_name() == Some("config");
+    config::init(&overrides, lenient)
+}
+
+/// Get how to confirm destructive operations from `--yes` and `--dry-run`.
+fn get_confirmation(matches: &ArgMatches) -> Confirmation {
+    Confirmation {
+        yes: matches.is_present("yes"),
+        dry_run: matches.is_present("dry-run"),
+    }
+}
 
 /// Process argument `new`.
 pub fn new(matches: &ArgMatches) -> Result<()> {
@@ -19,20 +104,44 @@ pub fn new(matches: &ArgMatches) -> Result<()> {
             .interact_text()?,
     };
 
-    notes::create(&name, &category)?;
+    match matches.value_of("template") {
+        Some(template) => {
+            let mut vars = BTreeMap::new();
+            for var in matches.values_of("var").into_iter().flatten() {
+                let (key, value) = var
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Invalid variable \"{}\", expected name=value.", var))?;
+                vars.insert(key.trim().to_owned(), value.to_owned());
+            }
+            notes::create_from_template(
+                &name,
+                &category,
+                &get_tags(matches),
+                dates::now(),
+                template,
+                &vars,
+            )?;
+        }
+        None => notes::create(&name, &category, &get_tags(matches))?,
+    }
     Ok(())
 }
 
 /// Process argument `remove`.
 pub fn remove(matches: &ArgMatches) -> Result<()> {
-    if matches.is_present("date") {
+    let range = date_range(matches)?;
+    if matches.is_present("date") && range.is_unbounded() {
         let date: String = match matches.value_of("name") {
             Some(s) => s.to_string(),
             None => Input::with_theme(&ColorfulTheme::default())
                 .with_prompt("Date")
                 .interact_text()?,
         };
-        return notes::remove_by_date(&date);
+        let range = DateRange::parse(Some(&date), None, None)?;
+        return notes::remove_by_date(&range, &get_confirmation(matches));
+    }
+    if !range.is_unbounded() {
+        return notes::remove_by_date(&range, &get_confirmation(matches));
     }
     if matches.is_present("category") {
         let category: String = match matches.value_of("name") {
@@ -41,7 +150,7 @@ pub fn remove(matches: &ArgMatches) -> Result<()> {
                 .with_prompt("Category")
                 .interact_text()?,
         };
-        return notes::remove_category(&category);
+        return notes::remove_category(&category, &get_confirmation(matches));
     }
     let name: String = match matches.value_of("name") {
         Some(s) => s.to_string(),
@@ -49,7 +158,11 @@ pub fn remove(matches: &ArgMatches) -> Result<()> {
             .with_prompt("Name")
             .interact_text()?,
     };
-    return notes::remove_note(&name);
+    notes::remove_note(
+        &name,
+        matches.is_present("exact"),
+        &get_confirmation(matches),
+    )
 }
 
 /// Process argument `remove`.
@@ -61,13 +174,14 @@ pub fn edit(matches: &ArgMatches) -> Result<()> {
             .interact_text()?,
     };
 
-    notes::modify(&name)?;
+    notes::modify(&name, matches.is_present("exact"))?;
     Ok(())
 }
 
 /// Process argument `list`.
 pub fn list(matches: &ArgMatches) -> Result<()> {
-    match matches.is_present("category") {
+    let format = get_format(matches)?;
+    let result = match matches.is_present("category") {
         true => {
             let name: String = match matches.value_of("name") {
                 Some(s) => s.to_string(),
@@ -75,15 +189,45 @@ pub fn list(matches: &ArgMatches) -> Result<()> {
                     .with_prompt("Category:")
                     .interact_text()?,
             };
-            notes::list_category(&name)?;
+            notes::list_category(
+                &name,
+                &tag_filter(matches),
+                &date_range(matches)?,
+                get_sort(matches)?,
+                format,
+            )
         }
-        false => notes::list_all_notes()?,
-    }
-    Ok(())
+        false => notes::list_all_notes(
+            &tag_filter(matches),
+            &date_range(matches)?,
+            get_sort(matches)?,
+            format,
+        ),
+    };
+    output::or_empty(result, format)
 }
 
 /// Process argument `search`.
 pub fn search(matches: &ArgMatches) -> Result<()> {
+    let format = get_format(matches)?;
+    output::or_empty(search_notes(matches, format), format)
+}
+
+/// Search notes as asked by the arguments of `search`.
+fn search_notes(matches: &ArgMatches, format: Option<Format>) -> Result<()> {
+    let regex = matches.is_present("regex");
+    let ignore_case = matches.is_present("ignore-case");
+    let whole_word = matches.is_present("whole-word");
+    if regex || ignore_case || whole_word {
+        let pattern: String = match matches.value_of("name") {
+            Some(s) => s.to_string(),
+            None => Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Pattern to search")
+                .interact_text()?,
+        };
+        let matcher = Matcher::new(&pattern, regex, ignore_case, whole_word)?;
+        return notes::grep(&matcher, &tag_filter(matches), format);
+    }
     if matches.is_present("word") {
         let word: String = match matches.value_of("name") {
             Some(s) => s.to_string(),
@@ -91,29 +235,32 @@ pub fn search(matches: &ArgMatches) -> Result<()> {
                 .with_prompt("String to search")
                 .interact_text()?,
         };
-        return notes::search_by_word(&word);
+        return notes::search_by_word(&word, &tag_filter(matches), format);
+    }
+    if matches.is_present("query") {
+        let query: String = match matches.value_of("name") {
+            Some(s) => s.to_string(),
+            None => Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Query")
+                .interact_text()?,
+        };
+        return notes::search_by_query(&query, &tag_filter(matches), format);
     }
 
     match matches.value_of("name") {
         Some(s) => {
-            let p = notes::get_note_path_interractive(s)?;
-            match p {
-                Some(s) => {
-                    let editor = std::env::var("EDITOR")?;
-                    std::process::Command::new(editor).arg(s).status()?;
-                }
-                None => return Err(anyhow!("Nothing found.")),
-            }
+            notes::search_by_name(s, &tag_filter(matches), matches.is_present("exact"), format)
         }
-        None => return Err(anyhow!("Nothing entered for search.")),
+        None => Err(anyhow!("Nothing entered for search.")),
     }
-    Ok(())
 }
 
 /// Process argument `show`.
 pub fn show(matches: &ArgMatches) -> Result<()> {
+    let filter = tag_filter(matches);
+    let range = date_range(matches)?;
     if matches.is_present("all") {
-        return notes::show_all();
+        return notes::show_all(&filter, &range);
     }
     if matches.is_present("category") {
         let category: String = match matches.value_of("name") {
@@ -122,23 +269,224 @@ pub fn show(matches: &ArgMatches) -> Result<()> {
                 .with_prompt("Category:")
                 .interact_text()?,
         };
-        return notes::show_category(&category);
+        return notes::show_category(&category, &filter, &range);
     }
 
     match matches.value_of("name") {
-        Some(s) => return notes::show(s),
+        Some(_) if !range.is_unbounded() => Err(anyhow!(
+            "Dates only select notes without a name, or in a category with --category."
+        )),
+        Some(s) => notes::show(s, &filter, matches.is_present("exact")),
+        None if !filter.tags.is_empty() || !range.is_unbounded() => {
+            notes::show_all(&filter, &range)
+        }
         None => {
             let s: String = Input::with_theme(&ColorfulTheme::default())
                 .with_prompt("String to search")
                 .interact_text()?;
-            return notes::show(&s);
+            notes::show(&s, &filter, matches.is_present("exact"))
         }
     }
 }
 
-/// Process argument `panic`.
-pub fn panic() -> Result<()> {
-    let base = notes::get_base_path()?;
-    std::fs::remove_dir_all(base)?;
+/// Process argument `tags`.
+pub fn tags(matches: &ArgMatches) -> Result<()> {
+    notes::list_tags(get_format(matches)?)
+}
+
+/// Process argument `reindex`.
+pub fn reindex() -> Result<()> {
+    let count = index::rebuild()?;
+    println!("Indexed {} notes.", count);
+    Ok(())
+}
+
+/// Process argument `migrate`.
+pub fn migrate(matches: &ArgMatches) -> Result<()> {
+    dates::migrate(&get_confirmation(matches))
+}
+
+/// Process argument `trash`.
+pub fn trash(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("restore", Some(m)) => {
+            let name: String = match m.value_of("name") {
+                Some(s) => s.to_string(),
+                None => Input::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Name")
+                    .interact_text()?,
+            };
+            trash::restore(&name)
+        }
+        ("empty", Some(m)) => {
+            let older_than = m
+                .value_of("older-than")
+                .map(dates::parse_duration)
+                .transpose()?;
+            trash::empty(older_than, &get_confirmation(m))
+        }
+        _ => trash::list(),
+    }
+}
+
+/// Get the note named by argument `name`, prompting for the name if missing
+/// and to choose a note if several match.
+fn get_note(matches: &ArgMatches) -> Result<Note> {
+    let name: String = match matches.value_of("name") {
+        Some(s) => s.to_string(),
+        None => Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Name")
+            .interact_text()?,
+    };
+    notes::get_note_interractive(&name, &TagFilter::default(), matches.is_present("exact"))?
+        .ok_or_else(|| anyhow!("No note chosen."))
+}
+
+/// Process argument `rename`.
+pub fn rename(matches: &ArgMatches) -> Result<()> {
+    let note = get_note(matches)?;
+    let (moved, relinked) =
+        notes::rename_note(&note, matches.value_of("new_name").unwrap_or_default())?;
+    notes::print_moved(&note, &moved, relinked);
     Ok(())
 }
+
+/// Process argument `move`.
+pub fn move_note(matches: &ArgMatches) -> Result<()> {
+    let note = get_note(matches)?;
+    let (moved, relinked) =
+        notes::move_note(&note, matches.value_of("category").unwrap_or_default())?;
+    notes::print_moved(&note, &moved, relinked);
+    Ok(())
+}
+
+/// Process argument `category`.
+pub fn category(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("rename", Some(m)) => category::rename(
+            m.value_of("old").unwrap_or_default(),
+            m.value_of("new").unwrap_or_default(),
+        ),
+        ("merge", Some(m)) => category::merge(
+            m.value_of("src").unwrap_or_default(),
+            m.value_of("dst").unwrap_or_default(),
+            m.is_present("suffix"),
+        ),
+        _ => category::list(),
+    }
+}
+
+/// Process argument `git`.
+pub fn git(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("init", _) => git::init(),
+        _ => match git::is_enabled()? {
+            true => {
+                println!("Versioning is enabled.");
+                Ok(())
+            }
+            false => {
+                println!("Versioning is disabled. Enable it with `rnote git init`.");
+                Ok(())
+            }
+        },
+    }
+}
+
+/// Process argument `history`.
+pub fn history(matches: &ArgMatches) -> Result<()> {
+    git::history(&get_note(matches)?)
+}
+
+/// Process argument `diff`.
+pub fn diff(matches: &ArgMatches) -> Result<()> {
+    git::diff(&get_note(matches)?, matches.value_of("rev"))
+}
+
+/// Process argument `restore`.
+pub fn restore(matches: &ArgMatches) -> Result<()> {
+    let rev = matches.value_of("rev").unwrap_or("HEAD");
+    git::restore(&get_note(matches)?, rev)
+}
+
+/// Process argument `config`.
+pub fn config(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("path", _) => {
+            println!("{}", config::path()?.display());
+            Ok(())
+        }
+        ("get", Some(m)) => config::print(m.value_of("key").unwrap_or_default()),
+        ("set", Some(m)) => config::set(
+            m.value_of("key").unwrap_or_default(),
+            m.value_of("value").unwrap_or_default(),
+        ),
+        _ => config::list(),
+    }
+}
+
+/// Process argument `notebook`.
+pub fn notebook(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("add", Some(m)) => notebook::add(
+            m.value_of("name").unwrap_or_default(),
+            m.value_of("path").unwrap_or_default(),
+        ),
+        ("remove", Some(m)) => notebook::remove(m.value_of("name").unwrap_or_default()),
+        ("default", Some(m)) => notebook::default(m.value_of("name")),
+        _ => notebook::list(),
+    }
+}
+
+/// Process argument `template`.
+pub fn template(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("show", Some(m)) => template::show(m.value_of("name").unwrap_or_default()),
+        ("edit", Some(m)) => template::edit(m.value_of("name").unwrap_or_default()),
+        _ => template::list(),
+    }
+}
+
+/// Process arguments `today`, `yesterday` and `journal`.
+pub fn journal(command: &str, matches: &ArgMatches) -> Result<()> {
+    let date = match (command, matches.value_of("date")) {
+        ("yesterday", _) => dates::today() - Duration::days(1),
+        (_, Some(date)) => journal::parse_day(date)?,
+        _ => dates::today(),
+    };
+    match matches.is_present("week") {
+        true => journal::show_week(date),
+        false => journal::open(date),
+    }
+}
+
+/// Process argument `calendar`.
+pub fn calendar(matches: &ArgMatches) -> Result<()> {
+    let month = match matches.value_of("month") {
+        Some(month) => journal::parse_month(month)?,
+        None => dates::today(),
+    };
+    journal::calendar(month)
+}
+
+/// Process argument `links`.
+pub fn links(matches: &ArgMatches) -> Result<()> {
+    links::links(
+        matches.value_of("name"),
+        matches.is_present("exact"),
+        matches.is_present("dangling"),
+    )
+}
+
+/// Process argument `backlinks`.
+pub fn backlinks(matches: &ArgMatches) -> Result<()> {
+    links::backlinks(
+        matches.value_of("name").unwrap_or_default(),
+        matches.is_present("exact"),
+    )
+}
+
+/// Process argument `panic`.
+pub fn panic(matches: &ArgMatches) -> Result<()> {
+    notes::remove_all(&get_confirmation(matches))
+}
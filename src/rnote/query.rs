@@ -0,0 +1,356 @@
+use crate::rnote::note::Note;
+use anyhow::{anyhow, Result};
+
+/// Fields that can be matched with `field:value`.
+const FIELDS: [&str; 5] = ["tag", "category", "title", "author", "date"];
+
+/// Parsed search query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// A word found anywhere in the note, ignoring case.
+    Term(String),
+    /// Several words found next to each other, ignoring case.
+    Phrase(String),
+    /// A metadata field matching a glob pattern.
+    Field(String, String),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Phrase(String),
+    Field(String, String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/// Split `input` into tokens, each with its position (1-based, in characters).
+fn lex(input: &str) -> Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens: Vec<(Token, usize)> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = i + 1;
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push((Token::Open, pos));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::Close, pos));
+                i += 1;
+            }
+            '-' => {
+                tokens.push((Token::Not, pos));
+                i += 1;
+            }
+            '"' => {
+                let (phrase, next) = read_quoted(&chars, i)?;
+                tokens.push((Token::Phrase(phrase), pos));
+                i = next;
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !is_delimiter(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => match word.split_once(':') {
+                        Some((field, value)) if FIELDS.contains(&field) => {
+                            let value = match value.is_empty() && chars.get(i) == Some(&'"') {
+                                true => {
+                                    let (value, next) = read_quoted(&chars, i)?;
+                                    i = next;
+                                    value
+                                }
+                                false => value.to_owned(),
+                            };
+                            if value.is_empty() {
+                                return Err(anyhow!(
+                                    "Invalid query at position {}: missing value for \"{}:\".",
+                                    pos,
+                                    field
+                                ));
+                            }
+                            Token::Field(field.to_owned(), value)
+                        }
+                        _ => Token::Word(word),
+                    },
+                };
+                tokens.push((token, pos));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+/// Read a quoted string starting at `chars[start]`, return it with the index after it.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize)> {
+    match chars[start + 1..].iter().position(|c| *c == '"') {
+        Some(len) => {
+            let s: String = chars[start + 1..start + 1 + len].iter().collect();
+            Ok((s, start + len + 2))
+        }
+        None => Err(anyhow!(
+            "Invalid query at position {}: unterminated quote.",
+            start + 1
+        )),
+    }
+}
+
+/// Recursive descent parser over the tokens of a query.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    /// Position of the current token, or the end of the input.
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.1)
+            .unwrap_or(self.len + 1)
+    }
+
+    fn error(&self, msg: &str) -> anyhow::Error {
+        anyhow!("Invalid query at position {}: {}.", self.position(), msg)
+    }
+
+    /// or := and ("OR" and)*
+    fn parse_or(&mut self) -> Result<Query> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Query::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// and := unary ("AND"? unary)*
+    fn parse_and(&mut self) -> Result<Query> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                _ => {}
+            }
+            let right = self.parse_unary()?;
+            left = Query::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// unary := ("-" | "NOT") unary | primary
+    fn parse_unary(&mut self) -> Result<Query> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Query::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    /// primary := "(" or ")" | word | phrase | field
+    fn parse_primary(&mut self) -> Result<Query> {
+        let token = match self.peek() {
+            Some(t) => t.clone(),
+            None => return Err(self.error("unexpected end of query")),
+        };
+        match token {
+            Token::Open => {
+                self.pos += 1;
+                let inner = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    return Err(self.error("expected \")\""));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Word(w) => {
+                self.pos += 1;
+                Ok(Query::Term(w))
+            }
+            Token::Phrase(p) => {
+                self.pos += 1;
+                Ok(Query::Phrase(p))
+            }
+            Token::Field(f, v) => {
+                self.pos += 1;
+                Ok(Query::Field(f, v))
+            }
+            Token::Close => Err(self.error("unexpected \")\"")),
+            Token::And | Token::Or => Err(self.error("operator without left operand")),
+            Token::Not => unreachable!(),
+        }
+    }
+}
+
+/// Parse a query such as `rust AND (tokio OR async) -draft "error handling" tag:work`.
+pub fn parse(input: &str) -> Result<Query> {
+    let tokens = lex(input)?;
+    if tokens.is_empty() {
+        return Err(anyhow!("Empty query."));
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        len: input.chars().count(),
+    };
+    let query = parser.parse_or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected \")\""));
+    }
+    Ok(query)
+}
+
+/// Match `text` against a glob `pattern` where `*` is any sequence and `?` any
+/// character, ignoring case.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let t: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+impl Query {
+    /// Words and phrases the query looks for, leaving out excluded ones.
+    pub fn terms(&self) -> Vec<String> {
+        match self {
+            Query::Term(w) | Query::Phrase(w) => vec![w.clone()],
+            Query::Field(..) | Query::Not(_) => Vec::new(),
+            Query::And(a, b) | Query::Or(a, b) => {
+                let mut terms = a.terms();
+                terms.extend(b.terms());
+                terms
+            }
+        }
+    }
+
+    /// Evaluate the query against a note.
+    pub fn matches(&self, note: &Note) -> bool {
+        match self {
+            Query::Term(w) | Query::Phrase(w) => {
+                let w = w.to_lowercase();
+                note.front.title.to_lowercase().contains(&w)
+                    || note.front.tags.iter().any(|t| t.to_lowercase() == w)
+                    || note.body.to_lowercase().contains(&w)
+            }
+            Query::Field(field, pattern) => match field.as_str() {
+                "tag" => note.front.tags.iter().any(|t| glob_match(pattern, t)),
+                "category" => glob_match(pattern, &note.category),
+                "title" => glob_match(pattern, &note.front.title),
+                "author" => glob_match(pattern, &note.front.author),
+                "date" => note
+                    .front
+                    .date
+                    .map(|d| glob_match(pattern, &d.format("%Y-%m-%d").to_string()))
+                    .unwrap_or(false),
+                _ => false,
+            },
+            Query::Not(q) => !q.matches(note),
+            Query::And(a, b) => a.matches(note) && b.matches(note),
+            Query::Or(a, b) => a.matches(note) || b.matches(note),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(s: &str) -> Box<Query> {
+        Box::new(Query::Term(s.to_owned()))
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            parse("rust AND (tokio OR async) -draft").unwrap(),
+            Query::And(
+                Box::new(Query::And(
+                    term("rust"),
+                    Box::new(Query::Or(term("tokio"), term("async")))
+                )),
+                Box::new(Query::Not(term("draft")))
+            )
+        );
+        assert_eq!(
+            parse("\"error handling\" tag:work category:2021-05-*").unwrap(),
+            Query::And(
+                Box::new(Query::And(
+                    Box::new(Query::Phrase("error handling".to_owned())),
+                    Box::new(Query::Field("tag".to_owned(), "work".to_owned()))
+                )),
+                Box::new(Query::Field("category".to_owned(), "2021-05-*".to_owned()))
+            )
+        );
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert!(parse("").is_err());
+        assert!(parse("rust AND").is_err());
+        assert!(parse("OR rust").is_err());
+        assert!(parse("(rust").is_err());
+        assert!(parse("rust)").is_err());
+        assert!(parse("\"rust").is_err());
+        assert!(parse("tag:").is_err());
+    }
+
+    #[test]
+    fn glob_match_test() {
+        assert!(glob_match("2021-05-*", "2021-05-03"));
+        assert!(glob_match("w?rk", "Work"));
+        assert!(!glob_match("2021-05-*", "2021-06-03"));
+    }
+
+    #[test]
+    fn matches_test() {
+        let mut note = Note::sample("2021-05-03", "a", "title: Errors\ntags: [work]");
+        note.body = "Rust error handling with tokio.".to_owned();
+        let q = parse(
+            "rust AND (tokio OR async) -draft \"error handling\" tag:work category:2021-05-*",
+        );
+        assert!(q.unwrap().matches(&note));
+        assert!(!parse("rust -tokio").unwrap().matches(&note));
+    }
+}
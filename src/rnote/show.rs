@@ -1,62 +1,578 @@
+use crate::rnote::{
+    config::{self, parse_color},
+    links::{self, find_links, Link},
+    note::Note,
+    notes,
+};
 use crossterm::{
-    cursor::{Hide, Show},
-    event::{self, Event, KeyCode::*, KeyEvent},
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyCode::*, KeyEvent, KeyModifiers},
     queue,
-    style::Color::*,
+    style::{Attribute, Print, SetAttribute},
     terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::io::{stderr, Write};
+use std::{
+    io::{stderr, Write},
+    ops::Range,
+    path::PathBuf,
+};
 use termimad::*;
 
-/// Set view area.
+/// Set view area, leaving the last line for the status line.
 pub fn view_area() -> Area {
     let mut area = Area::full_screen();
+    area.height = area.height.saturating_sub(1);
     area.pad_for_max_width(120); // we don't want a too wide text column
     area
 }
 
-/// Display the given markdown string `md` in a Scrollable TextView in a raw terminal.
-pub fn run_app(skin: MadSkin, md: &str) -> Result<()> {
-    let mut w = stderr(); // we could also have used stdout
-    queue!(w, EnterAlternateScreen)?;
-    terminal::enable_raw_mode()?;
-    queue!(w, Hide)?; // hiding the cursor
-    let mut view = MadView::from(md.to_owned(), view_area(), skin);
-    loop {
-        view.write_on(&mut w)?;
-        w.flush()?;
-        match event::read() {
-            Ok(Event::Key(KeyEvent { code, .. })) => match code {
-                Up => view.try_scroll_lines(-1),
-                Down => view.try_scroll_lines(1),
-                PageUp => view.try_scroll_pages(-1),
-                PageDown => view.try_scroll_pages(1),
-                _ => break,
-            },
-            Ok(Event::Resize(..)) => {
-                queue!(w, Clear(ClearType::All))?;
-                view.resize(&view_area());
+/// A document shown in the viewer.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub title: String,
+    pub markdown: String,
+    /// Notes shown, with the line each one starts on.
+    notes: Vec<(Note, usize)>,
+}
+
+impl Page {
+    /// Page showing `notes` one after the other.
+    pub fn from_notes(notes: &[Note]) -> Self {
+        let md: Vec<String> = notes.iter().map(|n| n.to_markdown()).collect();
+        let titles: Vec<&str> = notes.iter().map(|n| n.front.title.as_str()).collect();
+        let mut start = 0;
+        let mut starts = Vec::new();
+        for (note, md) in notes.iter().zip(&md) {
+            starts.push((note.clone(), start));
+            start += md.lines().count() + 1;
+        }
+        Page {
+            title: titles.join(", "),
+            markdown: md.join("---\n"),
+            notes: starts,
+        }
+    }
+
+    /// Page showing the notes of this page read again from disk, leaving out
+    /// the ones removed.
+    fn reload(&self) -> anyhow::Result<Self> {
+        let base = PathBuf::from(notes::get_base_path()?);
+        let notes: Vec<Note> = self
+            .notes
+            .iter()
+            .filter_map(|(n, _)| Note::load(&n.path, &base).ok())
+            .collect();
+        Ok(Page::from_notes(&notes))
+    }
+}
+
+/// Get the byte ranges of `line` matching `query`, ignoring case.
+fn find_matches(line: &str, query: &str) -> Vec<Range<usize>> {
+    let mut found = Vec::new();
+    if query.is_empty() {
+        return found;
+    }
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let mut chars = rest.char_indices();
+        let matched = query.chars().all(|q| match chars.next() {
+            Some((_, c)) => c.to_lowercase().eq(q.to_lowercase()),
+            None => false,
+        });
+        match matched {
+            true => {
+                let len = chars.next().map(|(j, _)| j).unwrap_or(rest.len());
+                found.push(i..i + len);
+                i += len;
+            }
+            false => i += rest.chars().next().map(char::len_utf8).unwrap_or(1),
+        }
+    }
+    found
+}
+
+/// Highlight the matches of `query` in `line` as inline code, leaving the
+/// ones already in inline code alone. Return if `line` has a match.
+fn highlight(line: &mut String, query: &str) -> bool {
+    let found = find_matches(line, query);
+    for range in found.iter().rev() {
+        let in_code = line[..range.start].matches('`').count() % 2 == 1;
+        if !in_code && !line[range.clone()].contains('`') {
+            let text = format!("`{}`", &line[range.clone()]);
+            line.replace_range(range.clone(), &text);
+        }
+    }
+    !found.is_empty()
+}
+
+/// Why a link to `target` resolving to the notes `found` cannot be followed.
+fn unfollowable(target: &str, found: &[&Note]) -> String {
+    match found.is_empty() {
+        true => format!("No note for [[{}]]", target),
+        false => {
+            let paths: Vec<String> = found.iter().map(|n| n.relative_path()).collect();
+            format!("[[{}]] is ambiguous: {}", target, paths.join(", "))
+        }
+    }
+}
+
+/// A page left by following a link, with where it was scrolled to.
+struct Visited {
+    page: Page,
+    scroll: i32,
+    focus: Option<usize>,
+}
+
+/// State of the viewer.
+struct Viewer {
+    skin: MadSkin,
+    page: Page,
+    view: MadView,
+    /// Page as displayed, with links and matches highlighted.
+    text: String,
+    /// Number of lines of the displayed page.
+    height: i32,
+    /// Links of the page, in order of appearance.
+    links: Vec<Link>,
+    /// Index of the selected link.
+    focus: Option<usize>,
+    /// Pages to go back to, the last one first.
+    history: Vec<Visited>,
+    /// Text searched for, highlighted in the page.
+    query: String,
+    /// Whether the query is being typed.
+    typing: bool,
+    /// Lines of the displayed page matching the query, counting from 0.
+    matches: Vec<usize>,
+    /// Index of the match jumped to.
+    current: Option<usize>,
+    /// Scroll when the search started, to look for matches from.
+    search_scroll: i32,
+    /// Message shown on the status line until the next key.
+    message: Option<String>,
+}
+
+impl Viewer {
+    fn new(skin: MadSkin, page: Page) -> Self {
+        let view = MadView::from(page.markdown.clone(), view_area(), skin.clone());
+        let mut viewer = Viewer {
+            skin,
+            page,
+            view,
+            text: String::new(),
+            height: 0,
+            links: Vec::new(),
+            focus: None,
+            history: Vec::new(),
+            query: String::new(),
+            typing: false,
+            matches: Vec::new(),
+            current: None,
+            search_scroll: 0,
+            message: None,
+        };
+        viewer.load(None, 0);
+        viewer
+    }
+
+    /// Show the current page with the link `focus` selected, scrolled to
+    /// `scroll`.
+    fn load(&mut self, focus: Option<usize>, scroll: i32) {
+        self.links = find_links(&self.page.markdown);
+        self.focus = focus.filter(|f| *f < self.links.len());
+        self.current = None;
+        self.refresh();
+        self.view.scroll = scroll;
+        self.view.try_scroll_lines(0);
+    }
+
+    /// Markdown of the page with links showing their text, the selected one
+    /// and the matches of the query highlighted, and the lines matching.
+    fn render(&self) -> (String, Vec<usize>) {
+        let mut lines: Vec<String> = self.page.markdown.lines().map(String::from).collect();
+        for (i, link) in self.links.iter().enumerate().rev() {
+            let text = match self.focus == Some(i) {
+                true => format!("`▶ {}`", link.text),
+                false => format!("[[{}]]", link.text),
+            };
+            if let Some(line) = lines.get_mut(link.line - 1) {
+                line.replace_range(link.start..link.end, &text);
+            }
+        }
+        let mut matches = Vec::new();
+        let mut in_code = false;
+        for (i, line) in lines.iter_mut().enumerate() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                continue;
+            }
+            let found = match in_code {
+                true => !find_matches(line, &self.query).is_empty(),
+                false => highlight(line, &self.query),
+            };
+            if found {
+                matches.push(i);
+            }
+        }
+        (lines.join("\n"), matches)
+    }
+
+    /// Render the page again, keeping the scroll.
+    fn refresh(&mut self) {
+        let scroll = self.view.scroll;
+        let (text, matches) = self.render();
+        self.height = self.skin.area_text(&text, &view_area()).lines.len() as i32;
+        self.view = MadView::from(text.clone(), view_area(), self.skin.clone());
+        self.view.scroll = scroll;
+        self.text = text;
+        self.matches = matches;
+        self.current = self.current.filter(|c| *c < self.matches.len());
+    }
+
+    /// Get the displayed line where the line `line` of the page starts.
+    fn display_line(&self, line: usize) -> i32 {
+        let before: Vec<&str> = self.text.lines().take(line).collect();
+        self.skin
+            .area_text(&before.join("\n"), &view_area())
+            .lines
+            .len() as i32
+    }
+
+    /// Scroll so that the line `line` of the page is visible.
+    fn scroll_to_line(&mut self, line: usize) {
+        let line = self.display_line(line);
+        let height = i32::from(view_area().height);
+        if line < self.view.scroll || line >= self.view.scroll + height {
+            self.view.scroll = (line - height / 3).max(0);
+            self.view.try_scroll_lines(0);
+        }
+    }
+
+    /// Select the next link, or the previous one if `forward` is false, and
+    /// scroll to it.
+    fn cycle_links(&mut self, forward: bool) {
+        let count = self.links.len();
+        if count == 0 {
+            return;
+        }
+        self.focus = Some(match (self.focus, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(f), true) => (f + 1) % count,
+            (Some(f), false) => (f + count - 1) % count,
+        });
+        self.refresh();
+        if let Some(link) = self.focus.and_then(|f| self.links.get(f)) {
+            self.scroll_to_line(link.line - 1);
+        }
+    }
+
+    /// Open the note the selected link points to.
+    fn follow(&mut self) -> anyhow::Result<()> {
+        let link = match self.focus.and_then(|f| self.links.get(f)) {
+            Some(link) => link,
+            None => return Ok(()),
+        };
+        let all = notes::get_all_notes()?;
+        let target = match links::resolve(&link.target, &all)[..] {
+            [note] => note.clone(),
+            ref found => {
+                self.message = Some(unfollowable(&link.target, found));
+                return Ok(());
+            }
+        };
+        let page = Page::from_notes(&[target]);
+        let left = std::mem::replace(&mut self.page, page);
+        self.history.push(Visited {
+            page: left,
+            scroll: self.view.scroll,
+            focus: self.focus,
+        });
+        self.load(None, 0);
+        Ok(())
+    }
+
+    /// Go back to the page visited before.
+    fn back(&mut self) {
+        if let Some(visited) = self.history.pop() {
+            self.page = visited.page;
+            self.load(visited.focus, visited.scroll);
+        }
+    }
+
+    /// Get the note shown at the top of the view.
+    fn current_note(&self) -> Option<&Note> {
+        self.page
+            .notes
+            .iter()
+            .rev()
+            .find(|(_, start)| self.display_line(*start) <= self.view.scroll)
+            .or_else(|| self.page.notes.first())
+            .map(|(note, _)| note)
+    }
+
+    /// Open the note shown at the top of the view in the editor, then show
+    /// it again as saved.
+    fn edit<W: Write>(&mut self, w: &mut W) -> anyhow::Result<()> {
+        let note = match self.current_note() {
+            Some(note) => note.clone(),
+            None => return Ok(()),
+        };
+        suspend(w, || notes::edit_note(&note))?;
+        self.page = self.page.reload()?;
+        self.load(self.focus, self.view.scroll);
+        Ok(())
+    }
+
+    /// Highlight `query` and jump to its first match below where the search
+    /// started.
+    fn search(&mut self, query: String) {
+        self.query = query;
+        self.refresh();
+        let scroll = self.search_scroll;
+        self.current = self
+            .matches
+            .iter()
+            .position(|m| self.display_line(*m) >= scroll)
+            .or_else(|| (!self.matches.is_empty()).then_some(0));
+        match self.current {
+            Some(c) => self.scroll_to_line(self.matches[c]),
+            None => {
+                self.view.scroll = scroll;
+                self.view.try_scroll_lines(0);
+            }
+        }
+    }
+
+    /// Jump to the next match, or the previous one if `forward` is false.
+    fn cycle_matches(&mut self, forward: bool) {
+        let count = self.matches.len();
+        if count == 0 {
+            return;
+        }
+        let current = match (self.current, forward) {
+            (None, _) => 0,
+            (Some(c), true) => (c + 1) % count,
+            (Some(c), false) => (c + count - 1) % count,
+        };
+        self.current = Some(current);
+        self.scroll_to_line(self.matches[current]);
+    }
+
+    /// Write the status line: the query being typed, else the title of the
+    /// note at the top and the scroll position.
+    fn write_status<W: Write>(&self, w: &mut W) -> anyhow::Result<()> {
+        let width = usize::from(terminal::size()?.0);
+        let status = match (self.typing, &self.message) {
+            (true, _) => format!("/{}", self.query),
+            (false, Some(message)) => format!(" {}", message),
+            (false, None) => {
+                let height = i32::from(view_area().height);
+                let last = (self.view.scroll + height).min(self.height);
+                let mut position = match self.height <= height {
+                    true => "All".to_owned(),
+                    false => format!(
+                        "{}-{}/{} {}%",
+                        self.view.scroll + 1,
+                        last,
+                        self.height,
+                        last * 100 / self.height.max(1)
+                    ),
+                };
+                if !self.query.is_empty() {
+                    let found = match self.current {
+                        Some(c) => format!("match {}/{}", c + 1, self.matches.len()),
+                        None => "no match".to_owned(),
+                    };
+                    position = format!("{}  {}", found, position);
+                }
+                let room = width.saturating_sub(position.chars().count() + 3);
+                let title = match self.current_note() {
+                    Some(note) => &note.front.title,
+                    None => &self.page.title,
+                };
+                let title: String = title.chars().take(room).collect();
+                format!(
+                    " {}{}{} ",
+                    title,
+                    " ".repeat(room - title.chars().count() + 1),
+                    position
+                )
+            }
+        };
+        write_status_line(w, &status)
+    }
+
+    /// Handle a key while typing the query.
+    fn type_query(&mut self, code: KeyCode) {
+        let mut query = self.query.clone();
+        match code {
+            Enter => self.typing = false,
+            Esc => {
+                self.typing = false;
+                self.search(String::new());
+            }
+            Backspace if query.is_empty() => self.typing = false,
+            Backspace => {
+                query.pop();
+                self.search(query);
+            }
+            Char(c) => {
+                query.push(c);
+                self.search(query);
             }
             _ => {}
         }
     }
+
+    /// Handle keys until the viewer is closed.
+    fn run<W: Write>(&mut self, w: &mut W) -> anyhow::Result<()> {
+        loop {
+            self.view.write_on(w)?;
+            self.write_status(w)?;
+            w.flush()?;
+            let half = i32::from(view_area().height) / 2;
+            let event = event::read();
+            if let Ok(Event::Key(_)) = event {
+                self.message = None;
+            }
+            match event {
+                Ok(Event::Key(KeyEvent { code, .. })) if self.typing => self.type_query(code),
+                Ok(Event::Key(KeyEvent { code, modifiers })) => {
+                    match (code, modifiers.contains(KeyModifiers::CONTROL)) {
+                        (Char('c'), true) | (Char('q'), false) | (Esc, _) => return Ok(()),
+                        (Char('d'), true) => self.view.try_scroll_lines(half),
+                        (Char('u'), true) => self.view.try_scroll_lines(-half),
+                        (_, true) => {}
+                        (Up, _) | (Char('k'), _) => self.view.try_scroll_lines(-1),
+                        (Down, _) | (Char('j'), _) => self.view.try_scroll_lines(1),
+                        (PageUp, _) | (Char('b'), _) => self.view.try_scroll_pages(-1),
+                        (PageDown, _) | (Char(' '), _) => self.view.try_scroll_pages(1),
+                        (Home, _) | (Char('g'), _) => self.view.scroll = 0,
+                        (End, _) | (Char('G'), _) => {
+                            self.view.scroll = self.height;
+                            self.view.try_scroll_lines(0);
+                        }
+                        (Char('/'), _) => {
+                            self.typing = true;
+                            self.search_scroll = self.view.scroll;
+                            self.search(String::new());
+                        }
+                        (Char('n'), _) => self.cycle_matches(true),
+                        (Char('N'), _) => self.cycle_matches(false),
+                        (Tab, _) => self.cycle_links(true),
+                        (BackTab, _) => self.cycle_links(false),
+                        (Enter, _) => self.follow()?,
+                        (Char('e'), _) => self.edit(w)?,
+                        (Backspace, _) => self.back(),
+                        _ => {}
+                    }
+                }
+                Ok(Event::Resize(..)) => {
+                    queue!(w, Clear(ClearType::All))?;
+                    self.view.resize(&view_area());
+                    self.refresh();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Write `status` in reverse video on the last line of the terminal.
+pub fn write_status_line<W: Write>(w: &mut W, status: &str) -> anyhow::Result<()> {
+    let (width, rows) = terminal::size()?;
+    let width = usize::from(width);
+    let status: String = status.chars().take(width).collect();
+    queue!(
+        w,
+        MoveTo(0, rows.saturating_sub(1)),
+        Clear(ClearType::CurrentLine),
+        SetAttribute(Attribute::Reverse),
+        Print(format!("{:width$}", status, width = width)),
+        SetAttribute(Attribute::Reset)
+    )?;
+    Ok(())
+}
+
+/// Leave the raw terminal to run `f`, e.g. an editor, then come back.
+pub fn suspend<W: Write, T>(w: &mut W, f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    terminal::disable_raw_mode()?;
+    queue!(w, Show, LeaveAlternateScreen)?;
+    w.flush()?;
+    let result = f();
+    queue!(w, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+    terminal::enable_raw_mode()?;
+    result
+}
+
+/// Show `page` in the viewer until it is closed, in a terminal already set up
+/// by `run_app`.
+pub fn view<W: Write>(w: &mut W, skin: MadSkin, page: Page) -> anyhow::Result<()> {
+    queue!(w, Clear(ClearType::All))?;
+    Viewer::new(skin, page).run(w)
+}
+
+/// Run `f` in a raw terminal on the alternate screen, restoring the terminal
+/// even if it fails.
+pub fn in_terminal(
+    f: impl FnOnce(&mut std::io::Stderr) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut w = stderr(); // we could also have used stdout
+    queue!(w, EnterAlternateScreen)?;
+    terminal::enable_raw_mode()?;
+    queue!(w, Hide)?; // hiding the cursor
+    let result = f(&mut w);
     terminal::disable_raw_mode()?;
     queue!(w, Show)?; // we must restore the cursor
     queue!(w, LeaveAlternateScreen)?;
     w.flush()?;
-    Ok(())
+    result
+}
+
+/// Display `page` in a scrollable TextView in a raw terminal, following its
+/// links on demand.
+pub fn run_app(skin: MadSkin, page: Page) -> anyhow::Result<()> {
+    in_terminal(|w| Viewer::new(skin, page).run(w))
 }
 
 /// Set MadSkin.
-pub fn make_skin() -> MadSkin {
+pub fn make_skin() -> anyhow::Result<MadSkin> {
+    let colors = &config::get().skin;
     let mut skin = MadSkin::default();
     skin.table.align = Alignment::Center;
-    skin.set_headers_fg(AnsiValue(178));
-    skin.bold.set_fg(Green);
-    skin.italic.set_fg(Blue);
-    skin.strikeout.set_fg(Red);
-    skin.set_headers_fg(rgb(231, 195, 115));
-    skin.scrollbar.thumb.set_fg(AnsiValue(178));
+    skin.bold.set_fg(parse_color(&colors.bold)?);
+    skin.italic.set_fg(parse_color(&colors.italic)?);
+    skin.strikeout.set_fg(parse_color(&colors.strikeout)?);
+    skin.set_headers_fg(parse_color(&colors.headers)?);
+    skin.scrollbar.thumb.set_fg(parse_color(&colors.scrollbar)?);
     skin.code_block.align = Alignment::Center;
-    skin
+    Ok(skin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_test() {
+        assert_eq!(find_matches("Plan the PLAN", "plan"), vec![0..4, 9..13]);
+        assert!(find_matches("plan", "").is_empty());
+        let mut line = "Été, `été` and été".to_owned();
+        assert!(highlight(&mut line, "ÉTÉ"));
+        assert_eq!(line, "`Été`, `été` and `été`");
+        assert!(!highlight(&mut line, "winter"));
+    }
+
+    #[test]
+    fn unfollowable_test() {
+        let note = |category: &str| Note::sample(category, "plan", "title: plan");
+        assert_eq!(unfollowable("plan", &[]), "No note for [[plan]]");
+        let (home, work) = (note("home"), note("work"));
+        assert_eq!(
+            unfollowable("plan", &[&home, &work]),
+            "[[plan]] is ambiguous: home/plan.md, work/plan.md"
+        );
+    }
 }
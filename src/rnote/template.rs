@@ -0,0 +1,183 @@
+use crate::rnote::{config, notes::open_in_editor};
+use anyhow::{anyhow, Result};
+use dialoguer::{theme::ColorfulTheme, Input};
+use std::{collections::BTreeMap, fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+/// Placeholder marking where the editor cursor starts.
+const CURSOR: &str = "cursor";
+
+/// Content of a template created by `rnote template edit`.
+const STARTER: &str = "---\ntitle: {{title}}\nauthor: {{user}}\n---\n{{cursor}}\n";
+
+/// Get the path of the template `name`.
+fn template_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') {
+        return Err(anyhow!("Invalid template name \"{}\".", name));
+    }
+    let config = config::get();
+    Ok(PathBuf::from(config.templates_dir()?).join(format!("{}.{}", name, config.extension)))
+}
+
+/// Read the template `name`.
+pub fn load(name: &str) -> Result<String> {
+    let path = template_path(name)?;
+    fs::read_to_string(&path).map_err(|_| {
+        anyhow!(
+            "No template named \"{}\". Create it with `rnote template edit {}`.",
+            name,
+            name
+        )
+    })
+}
+
+/// Find the first placeholder of `text`, returning the byte range of the
+/// whole `{{ name }}` and its trimmed name.
+fn next_placeholder(text: &str) -> Option<(usize, usize, &str)> {
+    let start = text.find("{{")?;
+    let end = start + 2 + text[start + 2..].find("}}")?;
+    Some((start, end + 2, text[start + 2..end].trim()))
+}
+
+/// Get the names of the placeholders of `template` to fill, in order of
+/// appearance, leaving out the cursor.
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some((_, end, name)) = next_placeholder(rest) {
+        if !name.is_empty() && name != CURSOR && !names.iter().any(|n| n == name) {
+            names.push(name.to_owned());
+        }
+        rest = &rest[end..];
+    }
+    names
+}
+
+/// Replace every placeholder of `template` found in `vars` by its value.
+pub fn render(template: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some((start, end, name)) = next_placeholder(rest) {
+        out.push_str(&rest[..start]);
+        match vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Fill `template` with `vars`, prompting for the value of every other
+/// placeholder but the cursor.
+pub fn fill(template: &str, mut vars: BTreeMap<String, String>) -> Result<String> {
+    for name in placeholders(template) {
+        if vars.contains_key(&name) {
+            continue;
+        }
+        let value: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(&name)
+            .allow_empty(true)
+            .interact_text()?;
+        vars.insert(name, value);
+    }
+    Ok(render(template, &vars))
+}
+
+/// Remove the cursor placeholder from `content`, returning the line it was on
+/// counting from 1.
+pub fn take_cursor(content: &str) -> (String, Option<usize>) {
+    let mut offset = 0;
+    while let Some((start, end, name)) = next_placeholder(&content[offset..]) {
+        let (start, end) = (offset + start, offset + end);
+        if name == CURSOR {
+            let line = content[..start].matches('\n').count() + 1;
+            return (
+                format!("{}{}", &content[..start], &content[end..]),
+                Some(line),
+            );
+        }
+        offset = end;
+    }
+    (content.to_owned(), None)
+}
+
+/// Print the name of every template.
+pub fn list() -> Result<()> {
+    let dir = PathBuf::from(config::get().templates_dir()?);
+    let mut names: Vec<String> = Vec::new();
+    if dir.exists() {
+        for file in fs::read_dir(&dir)? {
+            let path = file?.path();
+            let is_template = path
+                .extension()
+                .map(|e| *e == *config::get().extension)
+                .unwrap_or(false);
+            if let (true, Some(stem)) = (is_template, path.file_stem()) {
+                names.push(stem.to_string_lossy().into_owned());
+            }
+        }
+    }
+    if names.is_empty() {
+        println!(
+            "No templates in {}. Create one with `rnote template edit <name>`.",
+            dir.display()
+        );
+    }
+    names.sort();
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+/// Print the template `name`.
+pub fn show(name: &str) -> Result<()> {
+    print!("{}", load(name)?);
+    Ok(())
+}
+
+/// Open the template `name` in the editor, creating it if needed.
+pub fn edit(name: &str) -> Result<()> {
+    let path = template_path(name)?;
+    if !path.exists() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, STARTER)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    }
+    open_in_editor(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let template = "# {{title}}\n{{ who }} {{missing}} {{title}}\n{{cursor}}";
+        assert_eq!(placeholders(template), vec!["title", "who", "missing"]);
+        assert!(placeholders("{{ cursor }}").is_empty());
+        let mut vars = BTreeMap::new();
+        vars.insert("title".to_owned(), "Standup".to_owned());
+        vars.insert("who".to_owned(), "me".to_owned());
+        assert_eq!(
+            render(template, &vars),
+            "# Standup\nme {{missing}} Standup\n{{cursor}}"
+        );
+        assert_eq!(render("a {{b", &vars), "a {{b");
+    }
+
+    #[test]
+    fn take_cursor_test() {
+        let (content, line) = take_cursor("---\ntitle: a\n---\n\n{{cursor}}\n");
+        assert_eq!(content, "---\ntitle: a\n---\n\n\n");
+        assert_eq!(line, Some(5));
+        assert_eq!(take_cursor("no cursor").1, None);
+        assert_eq!(
+            take_cursor("{{title}}\na{{ cursor }}b\n"),
+            ("{{title}}\nab\n".to_owned(), Some(2))
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::rnote::{confirm::Confirmation, git, notes::get_base_path};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local};
+use dialoguer::{theme::ColorfulTheme, Select};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fmt, fs,
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+/// Name of the trash directory in the root directory of all notes.
+const TRASH_DIR: &str = ".trash";
+
+/// A note in the trash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    /// Name of the trashed file in the trash.
+    #[serde(skip)]
+    pub id: String,
+    /// Path of the note relative to the root of all notes before deletion.
+    pub original: String,
+    pub deleted: DateTime<Local>,
+}
+
+impl fmt::Display for TrashEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}  {}",
+            self.deleted.format("%Y-%m-%d %H:%M"),
+            self.original
+        )
+    }
+}
+
+/// Get the directories holding trashed files and their info.
+fn get_trash_dirs() -> Result<(PathBuf, PathBuf)> {
+    let trash = PathBuf::from(get_base_path()?).join(TRASH_DIR);
+    Ok((trash.join("files"), trash.join("info")))
+}
+
+/// Create the trash directories if needed.
+fn create_trash_dirs() -> Result<(PathBuf, PathBuf)> {
+    let (files, info) = get_trash_dirs()?;
+    for dir in [&files, &info] {
+        fs::create_dir_all(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    if let Some(trash) = files.parent() {
+        fs::set_permissions(trash, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok((files, info))
+}
+
+/// Move the note or category directory at `path` to the trash, recording
+/// where it came from.
+pub fn trash_note(path: &Path) -> Result<TrashEntry> {
+    let base = PathBuf::from(get_base_path()?);
+    let original = path
+        .strip_prefix(&base)
+        .map_err(|_| anyhow!("{} is not a note.", path.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let (files, info) = create_trash_dirs()?;
+    let deleted = Local::now();
+    let file_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let id = format!("{}_{}", deleted.format("%Y%m%d%H%M%S%f"), file_name);
+    let entry = TrashEntry {
+        id: id.clone(),
+        original,
+        deleted,
+    };
+    let mut f = fs::File::create(info.join(format!("{}.json", id)))?;
+    f.set_permissions(fs::Permissions::from_mode(0o600))?;
+    f.write_all(serde_json::to_string_pretty(&entry)?.as_bytes())?;
+    fs::rename(path, files.join(&id))?;
+    Ok(entry)
+}
+
+/// Get every note in the trash, most recently deleted first.
+pub fn get_entries() -> Result<Vec<TrashEntry>> {
+    let (files, info) = get_trash_dirs()?;
+    let mut entries: Vec<TrashEntry> = Vec::new();
+    if !info.exists() {
+        return Ok(entries);
+    }
+    for file in fs::read_dir(&info)? {
+        let path = file?.path();
+        let id = match path.file_stem() {
+            Some(s) => s.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        if !files.join(&id).exists() {
+            continue;
+        }
+        let mut entry: TrashEntry = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        entry.id = id;
+        entries.push(entry);
+    }
+    entries.sort_by_key(|e| Reverse(e.deleted));
+    Ok(entries)
+}
+
+/// Print every note in the trash.
+pub fn list() -> Result<()> {
+    let entries = get_entries()?;
+    if entries.is_empty() {
+        println!("Trash is empty.");
+    }
+    for entry in entries {
+        println!("{}", entry);
+    }
+    Ok(())
+}
+
+/// Move a trashed note back to where it was.
+fn restore_entry(entry: &TrashEntry) -> Result<()> {
+    let (files, info) = get_trash_dirs()?;
+    let base = PathBuf::from(get_base_path()?);
+    let target = base.join(&entry.original);
+    if target.exists() {
+        return Err(anyhow!(
+            "A note already exists at {}. Move it away first.",
+            entry.original
+        ));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+        fs::set_permissions(parent, fs::Permissions::from_mode(0o700))?;
+    }
+    fs::rename(files.join(&entry.id), &target)?;
+    fs::remove_file(info.join(format!("{}.json", entry.id)))?;
+    Ok(())
+}
+
+/// Restore the trashed note with the given name or original path, prompting
+/// to choose one if several match.
+pub fn restore(name: &str) -> Result<()> {
+    let mut entries: Vec<TrashEntry> = get_entries()?
+        .into_iter()
+        .filter(|e| {
+            let stem = Path::new(&e.original)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            stem == name || e.original == name
+        })
+        .collect();
+    let entry = match entries.len() {
+        0 => return Err(anyhow!("No note named \"{}\" in the trash.", name)),
+        1 => entries.remove(0),
+        _ => {
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Choose a note to restore")
+                .default(0)
+                .items(&entries)
+                .interact_opt()?;
+            match selection {
+                Some(s) => entries.remove(s),
+                None => {
+                    println!("Canceling...");
+                    return Ok(());
+                }
+            }
+        }
+    };
+    restore_entry(&entry)?;
+    git::commit(&format!("Restore {} from trash", entry.original))?;
+    println!("Restored {}.", entry.original);
+    Ok(())
+}
+
+/// Permanently delete notes from the trash once confirmed, only those deleted
+/// more than `older_than` ago if given.
+pub fn empty(older_than: Option<Duration>, confirmation: &Confirmation) -> Result<()> {
+    let (files, info) = get_trash_dirs()?;
+    let now = Local::now();
+    let entries: Vec<TrashEntry> = get_entries()?
+        .into_iter()
+        .filter(|e| older_than.map(|d| now - e.deleted >= d).unwrap_or(true))
+        .collect();
+    if entries.is_empty() {
+        println!("Nothing to delete.");
+        return Ok(());
+    }
+    let items: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+    if !confirmation.ask("permanently deleted", &items)? {
+        return Ok(());
+    }
+    for entry in &entries {
+        let path = files.join(&entry.id);
+        match path.is_dir() {
+            true => fs::remove_dir_all(&path)?,
+            false => fs::remove_file(&path)?,
+        }
+        fs::remove_file(info.join(format!("{}.json", entry.id)))?;
+    }
+    println!("Permanently deleted {} note(s).", entries.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rnote::notes::{create, get_notes_by_name};
+    use std::env;
+
+    #[test]
+    fn trash_restore_test() {
+        env::set_var("EDITOR", "echo");
+        assert!(create("test_trash", "test_trash", &[]).is_ok());
+        let note = get_notes_by_name("test_trash", true).unwrap().remove(0);
+        assert!(trash_note(&note.path).is_ok());
+        assert!(get_notes_by_name("test_trash", true).is_err());
+        assert!(restore("test_trash").is_ok());
+        assert!(get_notes_by_name("test_trash", true).is_ok());
+    }
+}